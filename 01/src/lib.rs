@@ -0,0 +1,133 @@
+#![deny(clippy::pedantic)]
+use std::{
+	cmp::{Ordering, Reverse},
+	collections::BinaryHeap,
+};
+
+/// A calorie total which isn't a whole number. Floats aren't [`Ord`] because of `NaN`, so these are ordered
+/// by [`f64::total_cmp`] instead, which puts positive `NaN`s above everything else.
+#[derive(Clone, Copy, Debug)]
+pub struct FloatCalories(pub f64);
+
+impl PartialEq for FloatCalories {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for FloatCalories {}
+
+impl PartialOrd for FloatCalories {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for FloatCalories {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.0.total_cmp(&other.0)
+	}
+}
+
+/// Find the `n` largest calorie totals, in descending order. If there are fewer than `n` totals, all of them are returned.
+pub fn top_n_calories<T: Ord>(calories: impl Iterator<Item = T>, n: usize) -> Vec<T> {
+	// Convenience for min-heap
+	let mut calorie_iter = calories.map(Reverse);
+
+	// Initialize a min-heap which keeps track of the n most total calories per elf, starting with the first n elves.
+	let mut heap = calorie_iter.by_ref().take(n).collect::<BinaryHeap<_>>();
+
+	// Then for each remaining elf, check to see if their total calories are one of the top n calories we've seen so far
+	// by comparing them to the numbers we've stored in the heap. A new calorie value will be one of the top n values we've seen so far
+	// if it's greater than *any* of the top n values we've previously seen. And if it's greater than *any* of them, it must be greater than
+	// the smallest one, which is stored on top of the min-heap. In that case, remove the smallest one and add the new value into the heap.
+	// Note the heap stores Reverse(x) so that it can be a min-heap, so the comparison is backwards.
+	// If n is 0, the heap is empty and there's nothing to keep, so stop without looking at the rest of the elves.
+	for x in calorie_iter {
+		match heap.peek() {
+			Some(smallest) if *smallest > x => {
+				heap.pop();
+				heap.push(x);
+			}
+			Some(_) => {}
+			None => break,
+		}
+	}
+
+	// Sorting the Reverse(x)s in ascending order puts the calories in descending order
+	heap.into_sorted_vec().into_iter().map(|x| x.0).collect()
+}
+
+/// The same as [`top_n_calories`], but calls `trace` after each total is looked at with its (1-based) position
+/// and the top totals so far, in descending order. This looks at every total, even if `n` is 0.
+pub fn top_n_calories_traced<T: Ord>(
+	calories: impl Iterator<Item = T>,
+	n: usize,
+	mut trace: impl FnMut(usize, &[&T]),
+) -> Vec<T> {
+	let mut heap = BinaryHeap::with_capacity(n);
+
+	// The same as top_n_calories, except that the first n totals are also looked at one at a time so that they can be traced
+	for (i, x) in calories.map(Reverse).enumerate() {
+		if heap.len() < n {
+			heap.push(x);
+		} else if heap.peek().is_some_and(|smallest| *smallest > x) {
+			heap.pop();
+			heap.push(x);
+		}
+
+		let mut top = heap.iter().map(|x| &x.0).collect::<Vec<_>>();
+		top.sort_unstable_by(|a, b| b.cmp(a));
+		trace(i + 1, &top);
+	}
+
+	heap.into_sorted_vec().into_iter().map(|x| x.0).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn top_n() {
+		let calories = [6000, 4000, 11000, 24000, 10000];
+
+		assert_eq!(top_n_calories(calories.into_iter(), 1), [24000]);
+		assert_eq!(
+			top_n_calories(calories.into_iter(), 3),
+			[24000, 11000, 10000]
+		);
+	}
+
+	#[test]
+	fn more_than_input() {
+		assert_eq!(
+			top_n_calories([6000, 4000, 11000].into_iter(), 5),
+			[11000, 6000, 4000]
+		);
+		assert!(top_n_calories(std::iter::empty::<u64>(), 3).is_empty());
+	}
+
+	#[test]
+	fn zero() {
+		assert!(top_n_calories([6000, 4000, 11000].into_iter(), 0).is_empty());
+	}
+
+	#[test]
+	fn ties() {
+		assert_eq!(
+			top_n_calories([5000, 7000, 5000, 7000, 5000].into_iter(), 3),
+			[7000, 7000, 5000]
+		);
+	}
+
+	#[test]
+	fn floats() {
+		let calories = [6000.5, 4000.25, 11000.0, 23999.75, 10000.5].map(FloatCalories);
+
+		assert_eq!(
+			top_n_calories(calories.into_iter(), 3),
+			[23999.75, 11000.0, 10000.5].map(FloatCalories)
+		);
+	}
+}
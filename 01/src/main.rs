@@ -1,77 +1,800 @@
 #![deny(clippy::pedantic)]
 use std::{
 	cmp::Reverse,
-	collections::BinaryHeap,
 	error::Error,
 	fs::File,
-	io::{self, BufRead},
+	io::{self, BufRead, Write},
+	ops::Add,
 	path::PathBuf,
+	str::FromStr,
 };
 
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use itertools::Itertools;
+use max_calories::{top_n_calories, top_n_calories_traced, FloatCalories};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 #[derive(Parser)]
 struct Args {
+	#[command(subcommand)]
+	command: Option<Command>,
 	/// Input file path
 	#[arg(short, long, default_value = "input.txt")]
 	input_file: PathBuf,
 	/// The number of elves to find with the maximum amount of calories.
-	/// Change to 1 for part 1 of the problem
-	#[arg(short, long, default_value_t = 3)]
-	num_elves: usize,
+	/// Change to 1 for part 1 of the problem. Can be given more than once to find multiple totals in one run
+	#[arg(short, long, default_values_t = [3])]
+	num_elves: Vec<usize>,
+	#[command(flatten)]
+	parsing: ParseArgs,
+	/// Exit with an error if any elf carries more than this many calories, after printing which elves they are
+	#[arg(long, value_name = "M")]
+	alert_over: Option<u64>,
+	/// Also print which elves are carrying the most calories, along with how many snacks they're carrying
+	#[arg(long, conflicts_with_all = ["alert_over", "float"])]
+	group_summary: bool,
+	/// Print the calories carried by the top elves so far after each elf is looked at, to follow how they are found
+	#[arg(long, conflicts_with_all = ["alert_over", "float", "group_summary"])]
+	trace: bool,
+	/// Instead of finding the elves with the most calories, print the calories carried at the P-th percentile (0-100) of every elf,
+	/// interpolating between the two closest elves
+	#[arg(
+		long,
+		value_name = "P",
+		value_parser = clap::value_parser!(u8).range(0..=100),
+		conflicts_with_all = ["alert_over", "float", "group_summary", "trace"]
+	)]
+	percentile: Option<u8>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-	let args = Args::parse();
+impl Args {
+	/// The options given which only apply when reading a single input file, and so can't be used with the merge subcommand
+	fn single_file_options(&self) -> Vec<&'static str> {
+		[
+			(self.alert_over.is_some(), "--alert-over"),
+			(self.group_summary, "--group-summary"),
+			(self.trace, "--trace"),
+			(self.percentile.is_some(), "--percentile"),
+		]
+		.into_iter()
+		.filter_map(|(given, option)| given.then_some(option))
+		.collect()
+	}
 
-	// Load input file, make sure it's openable
-	let file = File::open(args.input_file)?;
+	/// Exit with a usage error, as if clap had found the conflict, if any of the [`Args::single_file_options`] are given
+	fn check_merge_options(&self) {
+		let options = self.single_file_options();
+		if !options.is_empty() {
+			<Self as CommandFactory>::command()
+				.error(
+					ErrorKind::ArgumentConflict,
+					format!("{} can't be used with merge", options.join(", ")),
+				)
+				.exit();
+		}
+	}
+}
 
-	// Start reading file use a buffered reader
-	let mut calorie_iter = io::BufReader::new(file)
-		// Read by lines. Each line is either a single calorie number, or a separator (blank)
+#[derive(clap::Args)]
+struct ParseArgs {
+	/// The line which separates one elf's snacks from the next. Blank lines by default, in which case any other line
+	/// which isn't a number of calories separates elves as well. With any other separator, those lines are skipped instead
+	#[arg(long, default_value = "")]
+	separator: String,
+	/// Ignore lines starting with this prefix (such as `#`) entirely, as if they weren't in the input file
+	#[arg(long, value_name = "PREFIX")]
+	comment_prefix: Option<String>,
+	/// Allow calorie values with decimal places, rather than only whole numbers
+	#[arg(long, conflicts_with = "alert_over")]
+	float: bool,
+	/// Fail on any line which is neither a separator nor a whole number of calories made only of digits,
+	/// instead of skipping it
+	#[arg(long, conflicts_with = "float")]
+	strict: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Instead of finding the elves with the most calories, generate a random input file for benchmarking
+	Generate {
+		/// Output file path. Writes to stdout if not given
+		#[arg(short, long)]
+		output_file: Option<PathBuf>,
+		/// The number of elves to generate
+		#[arg(short, long)]
+		elves: usize,
+		/// The largest number of calories a single snack can have
+		#[arg(short, long, default_value_t = 10_000, value_parser = clap::value_parser!(u32).range(1..))]
+		max_snack: u32,
+		/// The largest number of snacks a single elf can carry
+		#[arg(long, default_value_t = 15, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+		max_snacks_per_elf: usize,
+		/// Seed for the random number generator, so that the same file can be generated again
+		#[arg(short, long, default_value_t = 0)]
+		seed: u64,
+	},
+	/// Instead of reading a single input file, read several one after the other as if they were one file,
+	/// and find the elves with the most calories among all of them. Elves never carry on from one file into the next
+	Merge {
+		/// Input file paths, in order
+		#[arg(required = true)]
+		input_files: Vec<PathBuf>,
+	},
+}
+
+/// Read the total calories carried by each elf from an input file, where elves are separated by lines equal to `separator`.
+/// Lines starting with `comment_prefix` are ignored.
+fn elf_calories<T: FromStr + Add<Output = T> + Copy, R: BufRead>(
+	reader: R,
+	separator: String,
+	comment_prefix: Option<String>,
+) -> impl Iterator<Item = T> {
+	elf_snacks(reader, separator, comment_prefix).map(|(_, calories)| calories)
+}
+
+/// The same as [`elf_calories`], but for calorie values with decimal places.
+/// A `NaN` snack would make its elf's total `NaN`, which isn't a meaningful amount of calories and would sort above every other elf,
+/// so those elves are skipped.
+fn float_elf_calories<R: BufRead>(
+	reader: R,
+	separator: String,
+	comment_prefix: Option<String>,
+) -> impl Iterator<Item = FloatCalories> {
+	elf_calories::<f64, _>(reader, separator, comment_prefix)
+		.filter(|calories| !calories.is_nan())
+		.map(FloatCalories)
+}
+
+/// The same as [`elf_calories`], but also counts the number of snacks each elf is carrying, as `(snacks, calories)`
+fn elf_snacks<T: FromStr + Add<Output = T> + Copy, R: BufRead>(
+	reader: R,
+	separator: String,
+	comment_prefix: Option<String>,
+) -> impl Iterator<Item = (usize, T)> {
+	reader
+		// Read by lines. Each line is either a single calorie number, or a separator
 		.lines()
 		// Reading a line can fail due to non-unicode characters being present in that line, so lines() returns an iterator over results of strings.
 		// I don't care about lines that have failed to read, so I skip them by flattening the iterator and end up with an iterator over just strings.
 		.flatten()
-		// Convert each line to a number. Blank separator lines will fail to parse, separating the iterator into runs of Ok(u32) snacks separated by Err(...) for each elf
-		.map(|l| l.parse::<u32>())
-		// Sum the runs of Ok(u32) into single Ok(u32) containing total calories for each elf alternating with Err(...)
-		.coalesce(|x, y| match (&x, &y) {
-			(Ok(x), Ok(y)) => Ok(Ok(x + y)),
+		// Drop comments before anything else, so that they can't be mistaken for separators or snacks
+		.filter(move |l| {
+			!comment_prefix
+				.as_ref()
+				.is_some_and(|prefix| l.starts_with(prefix.as_str()))
+		})
+		// Convert each line to a single snack, or None for separator lines, splitting the iterator into runs of Some((1, T)) snacks separated by None for each elf.
+		// Any other line which isn't a number is a separator as well when separating by blank lines, as blank lines are just lines which fail to parse.
+		// With any other separator, it's skipped.
+		.filter_map(move |l| {
+			if l == separator {
+				return Some(None);
+			}
+
+			match l.parse::<T>() {
+				Ok(x) => Some(Some((1, x))),
+				Err(_) if separator.is_empty() => Some(None),
+				Err(_) => None,
+			}
+		})
+		// Sum the runs of Some((1, T)) into single Some((snacks, T)) containing the number of snacks and total calories for each elf alternating with None
+		.coalesce(|x, y| match (x, y) {
+			(Some((n, x)), Some((m, y))) => Ok(Some((n + m, x + y))),
 			_ => Err((x, y)),
 		})
-		// Get rid of the Err(...) separators. Now we just have an iterator over total calories by elf.
+		// Get rid of the None separators. Now we just have an iterator over snacks and total calories by elf.
 		.flatten()
-		// Convenience for min-heap
-		.map(Reverse);
-
-	// Initialize a min-heap which keeps track of the n most total calories per elf, starting with the first n elves.
-	let mut heap = calorie_iter
-		.by_ref()
-		.take(args.num_elves)
-		.collect::<BinaryHeap<_>>();
-
-	// Then for each remaining elf, check to see if their total calories are one of the top n calories we've seen so far
-	// by comparing them to the numbers we've stored in the heap. A new calorie value will be one of the top n values we've seen so far
-	// if it's greater than *any* of the top n values we've previously seen. And if it's greater than *any* of them, it must be greater than
-	// the smallest one, which is stored on top of the min-heap. In that case, remove the smallest one and add the new value into the heap.
-	// Note the heap stores Reverse(x) so that it can be a min-heap, so the comparison is backwards.
-	calorie_iter.for_each(|x| {
-		if heap.peek().unwrap() > &x {
-			heap.pop();
-			heap.push(x);
+}
+
+/// Read the elves of several inputs one after the other, reading each input with `elves` (such as [`elf_calories`]).
+/// There's always a separator between inputs, so the last elf of one input and the first elf of the next are never joined together.
+fn merged_calories<R: BufRead, I: IntoIterator<Item = R>, E: Iterator>(
+	readers: I,
+	separator: String,
+	comment_prefix: Option<String>,
+	elves: impl Fn(R, String, Option<String>) -> E,
+) -> impl Iterator<Item = E::Item> {
+	readers
+		.into_iter()
+		// Each input is read separately, so each of its elves ends at the end of the input
+		.flat_map(move |reader| elves(reader, separator.clone(), comment_prefix.clone()))
+}
+
+/// Make sure every line of an input file is either a separator, a comment starting with `comment_prefix`, or a whole number of calories,
+/// written only with digits. Without checking, lines like `+1000`, `1,000`, or ` 1000` are either read as numbers or, as in [`elf_snacks`],
+/// treated as separators or skipped.
+fn check_strict<R: BufRead>(
+	reader: R,
+	separator: &str,
+	comment_prefix: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+	for (i, line) in reader.lines().enumerate() {
+		let line = line.map_err(|err| format!("Line {}: {err}", i + 1))?;
+
+		if comment_prefix.is_some_and(|prefix| line.starts_with(prefix)) {
+			continue;
+		}
+
+		let is_number = line.bytes().all(|c| c.is_ascii_digit()) && line.parse::<u64>().is_ok();
+		if line != separator && !is_number {
+			return Err(format!("Line {}: `{line}` is not a number of calories", i + 1).into());
+		}
+	}
+
+	Ok(())
+}
+
+/// Write a random input file with `elves` elves, each carrying between 1 and `max_snacks_per_elf` snacks
+/// of between 1 and `max_snack` calories.
+fn generate<W: Write, R: Rng>(
+	out: &mut W,
+	rng: &mut R,
+	elves: usize,
+	max_snack: u32,
+	max_snacks_per_elf: usize,
+) -> io::Result<()> {
+	for elf in 0..elves {
+		// Elves are separated by blank lines
+		if elf != 0 {
+			writeln!(out)?;
 		}
-	});
 
-	// Then once we've found the top n total calories per elf, sum them up and we have an answer
-	let calories: u32 = heap.into_iter().map(|x| x.0).sum();
+		for _ in 0..rng.gen_range(1..=max_snacks_per_elf) {
+			writeln!(out, "{}", rng.gen_range(1..=max_snack))?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Find the `n` elves carrying the most calories from each elf's `(snacks, calories)`, in descending order of calories.
+/// Each elf is given as their (1-based) position in the input, along with their snacks and calories.
+/// Elves carrying the same number of calories are in the same order as in the input.
+fn top_elves(elves: impl Iterator<Item = (usize, u64)>, n: usize) -> Vec<(usize, usize, u64)> {
+	top_n_calories(
+		elves
+			.enumerate()
+			// Ordering by calories first, and then by earliest position
+			.map(|(i, (snacks, calories))| (calories, Reverse(i + 1), snacks)),
+		n,
+	)
+	.into_iter()
+	.map(|(calories, Reverse(elf), snacks)| (elf, snacks, calories))
+	.collect()
+}
+
+/// Find every elf carrying more than `threshold` calories, as their (1-based) position in the input along with their total calories
+fn elves_over(calories: &[u64], threshold: u64) -> Vec<(usize, u64)> {
+	calories
+		.iter()
+		.enumerate()
+		.filter(|(_, calories)| **calories > threshold)
+		.map(|(i, calories)| (i + 1, *calories))
+		.collect()
+}
+
+/// The calories carried at the `percentile`th percentile of every elf, interpolating linearly between the two elves closest to it.
+/// The 0th and 100th percentiles are the elves carrying the least and most calories. Returns `None` if there are no elves.
+/// The percentile may fall part of the way between two elves, so it's given as whole calories along with hundredths of a calorie.
+fn percentile(calories: &mut [u64], percentile: u8) -> Option<(u64, u8)> {
+	let last = calories.len().checked_sub(1)?;
+	calories.sort_unstable();
 
-	println!(
-		"Calories carried by the top {} elves: {calories}",
-		args.num_elves
+	// Where the percentile falls among the sorted elves, in hundredths of an elf
+	let rank = usize::from(percentile) * last;
+	let (lower, upper) = (rank / 100, (rank / 100 + 1).min(last));
+	let fraction = u128::try_from(rank % 100).unwrap();
+
+	// In hundredths of a calorie
+	let value = u128::from(calories[lower]) * 100
+		+ (u128::from(calories[upper]) - u128::from(calories[lower])) * fraction;
+
+	// Interpolating never goes past the larger elf, so the whole calories fit
+	Some((
+		u64::try_from(value / 100).unwrap(),
+		u8::try_from(value % 100).unwrap(),
+	))
+}
+
+/// Print the calories carried at a percentile of every elf, as found by [`percentile`]
+fn print_percentile(calories: impl Iterator<Item = u64>, p: u8) -> Result<(), Box<dyn Error>> {
+	let (whole, hundredths) = percentile(&mut calories.collect::<Vec<_>>(), p)
+		.ok_or("There are no elves to find a percentile of")?;
+
+	println!("Calories carried at percentile {p}: {whole}.{hundredths:02}");
+
+	Ok(())
+}
+
+/// A line of the trace printed by `--trace`, with the calories of the top elves after the `elf`th elf
+fn trace_line(elf: usize, top: &[&u64]) -> String {
+	format!(
+		"After elf {elf}: {}",
+		top.iter().map(ToString::to_string).join(", ")
+	)
+}
+
+/// Find the elves with the most calories among several input files, read one after the other as in [`merged_calories`]
+fn merge(
+	input_files: &[PathBuf],
+	num_elves: &[usize],
+	parsing: &ParseArgs,
+) -> Result<(), Box<dyn Error>> {
+	let max_elves = num_elves.iter().copied().max().unwrap_or(0);
+	// Make sure every file is openable before reading any of them
+	let readers = input_files
+		.iter()
+		.map(|path| File::open(path).map(io::BufReader::new))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	if parsing.strict {
+		for path in input_files {
+			check_strict(
+				io::BufReader::new(File::open(path)?),
+				&parsing.separator,
+				parsing.comment_prefix.as_deref(),
+			)
+			.map_err(|err| format!("{}: {err}", path.display()))?;
+		}
+	}
+
+	let (separator, comment_prefix) = (parsing.separator.clone(), parsing.comment_prefix.clone());
+
+	if parsing.float {
+		let top_calories = top_n_calories(
+			merged_calories(readers, separator, comment_prefix, float_elf_calories),
+			max_elves,
+		);
+
+		for &num_elves in num_elves {
+			let calories: f64 = top_calories.iter().take(num_elves).map(|c| c.0).sum();
+
+			println!("Calories carried by the top {num_elves} elves: {calories}");
+		}
+
+		return Ok(());
+	}
+
+	let top_calories = top_n_calories(
+		merged_calories(readers, separator, comment_prefix, elf_calories::<u64, _>),
+		max_elves,
 	);
 
+	for &num_elves in num_elves {
+		let calories: u64 = top_calories.iter().take(num_elves).sum();
+
+		println!("Calories carried by the top {num_elves} elves: {calories}");
+	}
+
 	Ok(())
 }
+
+fn main() -> Result<(), Box<dyn Error>> {
+	let args = Args::parse();
+
+	if let Some(Command::Generate {
+		output_file,
+		elves,
+		max_snack,
+		max_snacks_per_elf,
+		seed,
+	}) = args.command
+	{
+		let mut rng = StdRng::seed_from_u64(seed);
+		let mut out: Box<dyn Write> = match output_file {
+			Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+			None => Box::new(io::BufWriter::new(io::stdout().lock())),
+		};
+
+		generate(&mut out, &mut rng, elves, max_snack, max_snacks_per_elf)?;
+
+		return Ok(());
+	}
+
+	if let Some(Command::Merge { input_files }) = &args.command {
+		args.check_merge_options();
+
+		return merge(input_files, &args.num_elves, &args.parsing);
+	}
+
+	// Load input file, make sure it's openable
+	let file = File::open(&args.input_file)?;
+	let separator = args.parsing.separator;
+	let comment_prefix = args.parsing.comment_prefix;
+
+	// Checking every line means going through the file twice, so only check when necessary
+	if args.parsing.strict {
+		check_strict(
+			io::BufReader::new(File::open(&args.input_file)?),
+			&separator,
+			comment_prefix.as_deref(),
+		)?;
+	}
+
+	// Start reading file use a buffered reader.
+	// We only need to go through the file once, finding as many elves as the largest number requested,
+	// and then the smaller numbers of elves are just the first few of those.
+	let max_elves = args.num_elves.iter().copied().max().unwrap_or(0);
+
+	if args.parsing.float {
+		let top_calories = top_n_calories(
+			float_elf_calories(io::BufReader::new(file), separator, comment_prefix),
+			max_elves,
+		);
+
+		for num_elves in args.num_elves {
+			let calories: f64 = top_calories.iter().take(num_elves).map(|c| c.0).sum();
+
+			println!("Calories carried by the top {num_elves} elves: {calories}");
+		}
+
+		return Ok(());
+	}
+
+	if args.group_summary {
+		let top_elves = top_elves(
+			elf_snacks::<u64, _>(io::BufReader::new(file), separator, comment_prefix),
+			max_elves,
+		);
+
+		for num_elves in args.num_elves {
+			let calories: u64 = top_elves
+				.iter()
+				.take(num_elves)
+				.map(|(_, _, calories)| calories)
+				.sum();
+
+			println!("Calories carried by the top {num_elves} elves: {calories}");
+		}
+
+		for (elf, snacks, calories) in top_elves {
+			println!("Elf {elf} is carrying {snacks} snacks totalling {calories} calories");
+		}
+
+		return Ok(());
+	}
+
+	let calories = elf_calories::<u64, _>(io::BufReader::new(file), separator, comment_prefix);
+
+	if let Some(p) = args.percentile {
+		return print_percentile(calories, p);
+	}
+
+	if args.trace {
+		let top_calories = top_n_calories_traced(calories, max_elves, |elf, top| {
+			println!("{}", trace_line(elf, top));
+		});
+
+		for num_elves in args.num_elves {
+			let calories: u64 = top_calories.iter().take(num_elves).sum();
+
+			println!("Calories carried by the top {num_elves} elves: {calories}");
+		}
+
+		return Ok(());
+	}
+
+	// Checking every elf against a threshold needs all of the totals, so only collect them when necessary
+	let (top_calories, over) = match args.alert_over {
+		Some(threshold) => {
+			let calories = calories.collect::<Vec<_>>();
+
+			(
+				top_n_calories(calories.iter().copied(), max_elves),
+				elves_over(&calories, threshold),
+			)
+		}
+		None => (top_n_calories(calories, max_elves), Vec::new()),
+	};
+
+	for num_elves in args.num_elves {
+		// Once we've found the top n total calories per elf, sum them up and we have an answer
+		let calories: u64 = top_calories.iter().take(num_elves).sum();
+
+		println!("Calories carried by the top {num_elves} elves: {calories}");
+	}
+
+	for (elf, calories) in &over {
+		println!("Elf {elf} is carrying {calories} calories");
+	}
+
+	// Exit non-zero if any elves were over the threshold
+	if over.is_empty() {
+		Ok(())
+	} else {
+		Err(format!("Elves over the alert threshold: {}", over.len()).into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// The example prompt
+	static EXAMPLE: &str = "1000
+2000
+3000
+
+4000
+
+5000
+6000
+
+7000
+8000
+9000
+
+10000";
+
+	#[test]
+	fn generated_elves() {
+		let mut rng = StdRng::seed_from_u64(1234);
+		let mut out = Vec::new();
+		generate(&mut out, &mut rng, 1000, 10_000, 15).unwrap();
+
+		assert_eq!(
+			elf_calories::<u64, _>(io::Cursor::new(out), String::new(), None).count(),
+			1000
+		);
+
+		// Every elf carries at least one snack of at least one calorie
+		let parse_generate = |options: &[&str]| {
+			Args::try_parse_from(
+				["max-calories", "generate", "--elves", "2"]
+					.iter()
+					.chain(options),
+			)
+		};
+		assert!(parse_generate(&["--max-snack", "0"]).is_err());
+		assert!(parse_generate(&["--max-snacks-per-elf", "0"]).is_err());
+		assert!(parse_generate(&["--max-snack", "1", "--max-snacks-per-elf", "1"]).is_ok());
+	}
+
+	#[test]
+	fn multiple_groupings() {
+		let top_calories = top_n_calories(
+			elf_calories::<u64, _>(io::Cursor::new(EXAMPLE), String::new(), None),
+			3,
+		);
+
+		assert_eq!(top_calories.iter().take(1).sum::<u64>(), 24000);
+		assert_eq!(top_calories.iter().take(3).sum::<u64>(), 45000);
+	}
+
+	#[test]
+	fn group_summary() {
+		let top_elves = top_elves(elf_snacks(io::Cursor::new(EXAMPLE), String::new(), None), 3);
+
+		assert_eq!(top_elves, [(4, 3, 24000), (3, 2, 11000), (5, 1, 10000)]);
+	}
+
+	#[test]
+	fn test_percentile() {
+		let mut calories = elf_calories::<u64, _>(io::Cursor::new(EXAMPLE), String::new(), None)
+			.collect::<Vec<_>>();
+
+		// The median of 4000, 6000, 10000, 11000 and 24000
+		assert_eq!(percentile(&mut calories, 50), Some((10000, 0)));
+		assert_eq!(percentile(&mut calories, 0), Some((4000, 0)));
+		assert_eq!(percentile(&mut calories, 100), Some((24000, 0)));
+		// 60% of the way from 11000 to 24000
+		assert_eq!(percentile(&mut calories, 90), Some((18800, 0)));
+
+		assert_eq!(percentile(&mut [1, 2], 25), Some((1, 25)));
+		assert_eq!(percentile(&mut [7], 30), Some((7, 0)));
+		assert_eq!(percentile(&mut [], 50), None);
+	}
+
+	#[test]
+	fn alert_over() {
+		let calories = elf_calories::<u64, _>(io::Cursor::new(EXAMPLE), String::new(), None)
+			.collect::<Vec<_>>();
+
+		assert_eq!(elves_over(&calories, 20000), [(4, 24000)]);
+		assert!(elves_over(&calories, 24000).is_empty());
+	}
+
+	#[test]
+	fn custom_separator() {
+		let example = "1000
+2000
+3000
+---
+4000
+---
+5000
+6000
+---
+7000
+8000
+9000
+---
+10000";
+		let top_calories = top_n_calories(
+			elf_calories::<u64, _>(io::Cursor::new(example), "---".to_owned(), None),
+			5,
+		);
+
+		assert_eq!(top_calories, vec![24000, 11000, 10000, 6000, 4000]);
+	}
+
+	#[test]
+	fn float_calories() {
+		let example = "1000.5
+2000.25
+
+4000
+
+5000.75
+6000.5";
+		let top_calories = top_n_calories(
+			elf_calories::<f64, _>(io::Cursor::new(example), String::new(), None)
+				.map(FloatCalories),
+			2,
+		);
+
+		assert_eq!(
+			top_calories,
+			[FloatCalories(11001.25), FloatCalories(4000.0)]
+		);
+	}
+
+	#[test]
+	fn float_nan() {
+		// The second elf has a NaN snack, so is skipped instead of being found as the elf with the most calories
+		let example = "1000.5
+2000.25
+
+NaN
+4000
+
+5000.75";
+		let top_calories = top_n_calories(
+			float_elf_calories(io::Cursor::new(example), String::new(), None),
+			3,
+		);
+
+		assert_eq!(
+			top_calories,
+			[FloatCalories(5000.75), FloatCalories(3000.75)]
+		);
+	}
+
+	#[test]
+	fn trace() {
+		let mut lines = Vec::new();
+		let top_calories = top_n_calories_traced(
+			elf_calories::<u64, _>(io::Cursor::new(EXAMPLE), String::new(), None),
+			3,
+			|elf, top| lines.push(trace_line(elf, top)),
+		);
+
+		assert_eq!(top_calories, [24000, 11000, 10000]);
+		assert_eq!(
+			lines,
+			[
+				"After elf 1: 6000",
+				"After elf 2: 6000, 4000",
+				"After elf 3: 11000, 6000, 4000",
+				"After elf 4: 24000, 11000, 6000",
+				"After elf 5: 24000, 11000, 10000",
+			]
+		);
+	}
+
+	#[test]
+	fn strict() {
+		let example = "1000
+1,000
+2000
+
+3000";
+
+		// By default, lines which aren't numbers separate elves, the same as blank lines
+		let calories = elf_calories::<u64, _>(io::Cursor::new(example), String::new(), None)
+			.collect::<Vec<_>>();
+		assert_eq!(calories, [1000, 2000, 3000]);
+
+		// With any other separator, they're skipped
+		let calories = elf_calories::<u64, _>(
+			io::Cursor::new(example.replace("\n\n", "\n---\n")),
+			"---".to_owned(),
+			None,
+		)
+		.collect::<Vec<_>>();
+		assert_eq!(calories, [3000, 3000]);
+
+		let err = check_strict(io::Cursor::new(example), "", None).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"Line 2: `1,000` is not a number of calories"
+		);
+
+		assert!(check_strict(io::Cursor::new(EXAMPLE), "", None).is_ok());
+		assert!(check_strict(io::Cursor::new("+1000"), "", None).is_err());
+		assert!(check_strict(io::Cursor::new(" 1000"), "", None).is_err());
+		assert!(check_strict(io::Cursor::new("1000\n---\n"), "", None).is_err());
+		assert!(check_strict(io::Cursor::new("1000\n---\n"), "---", None).is_ok());
+	}
+
+	#[test]
+	fn comments() {
+		let example = "# The first elf
+1000
+2000
+# Snacks which were eaten on the way
+3000
+
+# The second elf
+4000
+#5000";
+
+		let calories = elf_calories::<u64, _>(
+			io::Cursor::new(example),
+			String::new(),
+			Some("#".to_owned()),
+		)
+		.collect::<Vec<_>>();
+		assert_eq!(calories, [6000, 4000]);
+
+		// A comment which happens to look like a separator is still ignored, rather than splitting the first elf in two
+		let calories = elf_calories::<u64, _>(
+			io::Cursor::new("1000\n---\n2000\n--- 3000\n3000"),
+			"---".to_owned(),
+			Some("--- ".to_owned()),
+		)
+		.collect::<Vec<_>>();
+		assert_eq!(calories, [1000, 5000]);
+
+		assert!(check_strict(io::Cursor::new(example), "", Some("#")).is_ok());
+		assert!(check_strict(io::Cursor::new(example), "", None).is_err());
+	}
+
+	#[test]
+	fn merge() {
+		// The last elf of the first input would be joined to the first elf of the second input if they were simply concatenated,
+		// carrying 16000 calories and becoming the top elf
+		let first = "1000\n\n2000\n3000\n4000";
+		let second = "7000\n\n8000\n\n6000";
+
+		let calories = merged_calories(
+			[io::Cursor::new(first), io::Cursor::new(second)],
+			String::new(),
+			None,
+			elf_calories::<u64, _>,
+		)
+		.collect::<Vec<_>>();
+		assert_eq!(calories, [1000, 9000, 7000, 8000, 6000]);
+
+		let top_calories = top_n_calories(calories.into_iter(), 3);
+		assert_eq!(top_calories, [9000, 8000, 7000]);
+		assert_eq!(top_calories.iter().sum::<u64>(), 24000);
+
+		// Decimal places are kept with --float, rather than those snacks being skipped
+		let calories = merged_calories(
+			[
+				io::Cursor::new("1000.5\n\n2000"),
+				io::Cursor::new("3000.25"),
+			],
+			String::new(),
+			None,
+			float_elf_calories,
+		)
+		.collect::<Vec<_>>();
+		assert_eq!(
+			calories,
+			[
+				FloatCalories(1000.5),
+				FloatCalories(2000.0),
+				FloatCalories(3000.25)
+			]
+		);
+
+		let args = Args::parse_from(["max-calories", "--float", "merge", "a.txt", "b.txt"]);
+		assert!(args.single_file_options().is_empty());
+		let args = Args::parse_from(["max-calories", "--percentile", "50", "merge", "a.txt"]);
+		assert_eq!(args.single_file_options(), ["--percentile"]);
+		let args = Args::parse_from(["max-calories", "--trace", "merge", "a.txt"]);
+		assert_eq!(args.single_file_options(), ["--trace"]);
+	}
+}
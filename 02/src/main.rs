@@ -2,13 +2,24 @@
 #![deny(clippy::pedantic)]
 use std::{
 	fs::File,
-	io::{self, BufRead},
+	io::{self, BufRead, Write},
+	num::NonZeroUsize,
 	path::PathBuf,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use rand::{
+	distributions::{Distribution, WeightedIndex},
+	rngs::StdRng,
+	Rng, SeedableRng,
+};
+use rayon::{
+	prelude::{IntoParallelRefIterator, ParallelIterator},
+	ThreadPoolBuilder,
+};
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, ValueEnum)]
 enum Mode {
@@ -16,16 +27,166 @@ enum Mode {
 	Shape,
 	/// The second variant of the problem, where the second letter in each line of the file tells you how you should win
 	Win,
+	/// Ignore the second letter in each line, and find the best score possible by winning every round
+	Optimal,
+	/// Score the strategy guide both as shapes and as outcomes, and report how different the two scores are
+	Both,
+	/// Each line of the input file is only the opponent's shape, and we play according to a fixed strategy
+	Fixed,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+/// A fixed strategy for choosing our shape each round, in fixed mode
+enum Strategy {
+	/// Always play the shape which beats the opponent's shape
+	Win,
+	/// Always play the shape which loses to the opponent's shape
+	Lose,
+	/// Always play the same shape as the opponent
+	Draw,
+	/// Play whatever shape the opponent played last round, starting with rock
+	Mirror,
+	/// Play the shape which would have beaten the opponent's shape last round, starting with rock
+	Counter,
+}
+
+impl Strategy {
+	/// Our shape against the opponent's shape `p1`, given what the opponent played last round (if there was one).
+	/// Shapes are numbered as in [`score_shape`]
+	fn shape(self, p1: u8, previous: Option<u8>) -> u8 {
+		// As in score_shape, each shape beats the one before it
+		match self {
+			Strategy::Win => (p1 + 1) % 3,
+			Strategy::Lose => (p1 + 2) % 3,
+			Strategy::Draw => p1,
+			Strategy::Mirror => previous.unwrap_or(0),
+			Strategy::Counter => previous.map_or(0, |previous| (previous + 1) % 3),
+		}
+	}
+}
+
+#[derive(Clone, ValueEnum)]
+enum Format {
+	/// A table of how many rounds were lost, drawn, and won against each shape, after the total score
+	Plain,
+	/// The total score, and how many rounds were lost, drawn, and won against each shape along with the rate of each,
+	/// for use with other programs
+	Json,
 }
 
 #[derive(Parser)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
+	#[command(subcommand)]
+	command: Option<Command>,
 	/// Input file path
 	#[arg(short, long, default_value = "input.txt")]
 	input_file: PathBuf,
 	/// What mode to run the program in
-	#[arg(value_enum)]
-	mode: Mode,
+	#[arg(value_enum, required = true)]
+	mode: Option<Mode>,
+	/// Also print how many rounds were lost, drawn, and won against each of the opponent's shapes
+	#[arg(long)]
+	histogram: bool,
+	/// How to print the histogram
+	#[arg(long, value_enum, default_value_t = Format::Plain, requires = "histogram")]
+	format: Format,
+	/// Instead of scoring a strategy guide file, read rounds from stdin one line at a time and print the running total after each
+	#[arg(long, conflicts_with = "histogram")]
+	interactive: bool,
+	/// The letters the opponent uses for rock, paper, and scissors
+	#[arg(long, value_parser = parse_letters, default_value = "ABC")]
+	opponent_letters: [char; 3],
+	/// The letters used in the second column of each line, which are either shapes or outcomes depending on the mode
+	#[arg(long, value_parser = parse_letters, default_value = "XYZ")]
+	self_letters: [char; 3],
+	/// Score the rounds on this many threads at once, for very large strategy guides. Doesn't apply to both or fixed mode
+	#[arg(long, value_name = "N")]
+	threads: Option<NonZeroUsize>,
+	/// The strategy to play with in fixed mode
+	#[arg(long, value_enum, required_if_eq("mode", "fixed"))]
+	strategy: Option<Strategy>,
+}
+
+/// Parse the three letters of a column of the strategy guide, as given on the command line (such as `ABC`)
+fn parse_letters(text: &str) -> Result<[char; 3]> {
+	let letters: [char; 3] = text
+		.chars()
+		.collect::<Vec<_>>()
+		.try_into()
+		.map_err(|_| anyhow!("`{text}` is not exactly three letters"))?;
+
+	if letters.iter().any(|c| c.is_whitespace()) {
+		return Err(anyhow!("`{text}` contains whitespace"));
+	}
+	if letters[0] == letters[1] || letters[1] == letters[2] || letters[0] == letters[2] {
+		return Err(anyhow!("`{text}` has the same letter more than once"));
+	}
+
+	Ok(letters)
+}
+
+/// The letters used in each column of a strategy guide, if not the usual `A-C` and `X-Z`
+struct Letters {
+	opponent: [char; 3],
+	own: [char; 3],
+}
+
+impl Letters {
+	/// Rewrite a line of a strategy guide using these letters into the usual letters, so that it can be parsed as usual.
+	/// Lines which aren't rounds with these letters become blank, so that they're skipped.
+	fn translate(&self, line: &str) -> String {
+		let mut chars = line.trim().chars();
+
+		match (chars.next(), chars.next(), chars.next(), chars.next()) {
+			(Some(p1), Some(' '), Some(p2), None) => {
+				let p1 = self.opponent.iter().position(|c| *c == p1);
+				let p2 = self.own.iter().position(|c| *c == p2);
+
+				match (p1, p2) {
+					(Some(p1), Some(p2)) => {
+						format!("{} {}", char::from(b"ABC"[p1]), char::from(b"XYZ"[p2]))
+					}
+					_ => String::new(),
+				}
+			}
+			_ => String::new(),
+		}
+	}
+
+	/// Parse a line which is only the opponent's shape, such as `A`, into a 0-based input as expected by score_ functions.
+	/// Returns `None` for lines which aren't a single one of the opponent's letters.
+	fn opponent(&self, line: &str) -> Option<u8> {
+		let mut chars = line.trim().chars();
+
+		match (chars.next(), chars.next()) {
+			(Some(p1), None) => self
+				.opponent
+				.iter()
+				.position(|c| *c == p1)
+				.map(|p1| u8::try_from(p1).unwrap()),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Instead of scoring a strategy guide, generate a random one for benchmarking
+	Generate {
+		/// Output file path. Writes to stdout if not given
+		#[arg(short, long)]
+		output_file: Option<PathBuf>,
+		/// The number of rounds to generate
+		#[arg(short, long)]
+		rounds: usize,
+		/// Relative weights of losing, drawing, and winning each round, when the second letter is interpreted as a shape
+		#[arg(short, long, num_args = 3, default_values_t = [1, 1, 1])]
+		weights: Vec<u32>,
+		/// Seed for the random number generator, so that the same file can be generated again
+		#[arg(short, long, default_value_t = 0)]
+		seed: u64,
+	},
 }
 
 /// The first version of scoring, where the second player's input is the shape they should make.
@@ -60,33 +221,386 @@ fn score_win(p1: u8, p2: u8) -> u8 {
 	re.unwrap()
 }
 
+/// Scoring if we could choose our shape optimally. Player 2's input is ignored, and we always play to win.
+/// Only one shape beats any given shape, and a win (at least 7 points) always scores more than a draw or a loss (at most 6 points),
+/// so there is never more than one optimal shape to choose between.
+fn score_optimal(p1: u8, _p2: u8) -> u8 {
+	// 2 - win, as in score_win
+	score_win(p1, 2)
+}
+
+#[derive(Clone, Copy)]
+/// How a round turned out for us
+enum RoundOutcome {
+	Lose,
+	Draw,
+	Win,
+}
+
+impl RoundOutcome {
+	/// The outcome of a round where player 2's input is the shape they make, as in [`score_shape`]
+	fn shape(p1: u8, p2: u8) -> Self {
+		// Same logic as score_shape, but the other way around so the difference is in the same order as the variants
+		Self::from((i16::from(p2) - i16::from(p1) + 1).rem_euclid(3))
+	}
+
+	/// The outcome of a round where player 2's input is the outcome, as in [`score_win`]
+	fn win(_p1: u8, p2: u8) -> Self {
+		Self::from(i16::from(p2))
+	}
+
+	/// The outcome of a round where we always win, as in [`score_optimal`]
+	fn optimal(_p1: u8, _p2: u8) -> Self {
+		Self::Win
+	}
+}
+
+impl From<i16> for RoundOutcome {
+	/// 0 - lose, 1 - tie, 2 - win, as in [`score_win`]
+	fn from(outcome: i16) -> Self {
+		match outcome {
+			0 => Self::Lose,
+			1 => Self::Draw,
+			2 => Self::Win,
+			_ => unreachable!(),
+		}
+	}
+}
+
+/// Count the outcome of every round in a strategy guide against each of the opponent's shapes.
+/// Indexed first by the opponent's shape (as in [`score_shape`]), then by the [`RoundOutcome`].
+fn histogram<T: Iterator<Item = String>>(
+	lines: T,
+	outcome: fn(u8, u8) -> RoundOutcome,
+) -> [[u32; 3]; 3] {
+	let mut counts = [[0; 3]; 3];
+
+	for (p1, p2) in lines.filter_map(|line| parse_round(&line)) {
+		counts[usize::from(p1)][outcome(p1, p2) as usize] += 1;
+	}
+
+	counts
+}
+
+/// How the rounds against one of the opponent's shapes turned out, for exporting as JSON
+#[derive(Serialize, Deserialize)]
+struct ShapeStats {
+	/// The opponent's shape
+	shape: String,
+	/// The number of rounds against this shape
+	rounds: u32,
+	lose: u32,
+	draw: u32,
+	win: u32,
+	/// The fraction of rounds against this shape which were lost, or 0 if there weren't any rounds against it
+	lose_rate: f64,
+	draw_rate: f64,
+	win_rate: f64,
+}
+
+/// The total score of a strategy guide along with its histogram, for exporting as JSON
+#[derive(Serialize, Deserialize)]
+struct Report {
+	score: u32,
+	/// Rock, then paper, then scissors
+	shapes: Vec<ShapeStats>,
+}
+
+impl Report {
+	/// A report of a strategy guide with a total score of `score`, and outcome counts from [`histogram`]
+	fn new(score: u32, counts: [[u32; 3]; 3]) -> Self {
+		let shapes = ["rock", "paper", "scissors"]
+			.into_iter()
+			.zip(counts)
+			.map(|(shape, [lose, draw, win])| {
+				let rounds = lose + draw + win;
+				// No rounds against a shape means none of them were lost, drawn, or won
+				let rate = |count| f64::from(count) / f64::from(rounds.max(1));
+
+				ShapeStats {
+					shape: shape.to_string(),
+					rounds,
+					lose,
+					draw,
+					win,
+					lose_rate: rate(lose),
+					draw_rate: rate(draw),
+					win_rate: rate(win),
+				}
+			})
+			.collect();
+
+		Report { score, shapes }
+	}
+}
+
+/// Parse a single round of a strategy guide, such as `A Y`, into 0-based inputs as expected by score_ functions.
+/// Returns `None` for blank or malformed lines, rather than trying to index past the end of a short line.
+fn parse_round(line: &str) -> Option<(u8, u8)> {
+	match line.trim().as_bytes() {
+		[p1 @ b'A'..=b'C', b' ', p2 @ b'X'..=b'Z'] => Some((p1 - b'A', p2 - b'X')),
+		_ => None,
+	}
+}
+
+/// The score of every round with a given scoring method, indexed first by player 1's input and then by player 2's input
+type ScoreTable = [[u8; 3]; 3];
+
+/// Work out the score of all nine possible rounds up front, so that scoring a round is just a lookup
+fn score_table(score: fn(u8, u8) -> u8) -> ScoreTable {
+	let mut table = [[0; 3]; 3];
+
+	for (p1, row) in (0..).zip(&mut table) {
+		for (p2, entry) in (0..).zip(row) {
+			*entry = score(p1, p2);
+		}
+	}
+
+	table
+}
+
+/// Score every round in a strategy guide with the given scoring method, and sum them up
+fn total_score<T: Iterator<Item = String>>(lines: T, score: fn(u8, u8) -> u8) -> u32 {
+	let table = score_table(score);
+
+	lines
+		// Convert letters into 0-based inputs as expected by score_ functions, skipping lines which aren't rounds
+		.filter_map(|s| parse_round(&s))
+		// and then look up their scores depending on chosen scoring method
+		.map(|(p1, p2)| u32::from(table[usize::from(p1)][usize::from(p2)]))
+		// Then sum up the scores
+		.sum()
+}
+
+/// The same as [`total_score`], but the rounds are scored on `threads` threads at once.
+/// Every round is scored independently, so the total is the same as scoring them one at a time.
+fn par_total_score(
+	lines: &[String],
+	score: fn(u8, u8) -> u8,
+	threads: NonZeroUsize,
+) -> Result<u32> {
+	let pool = ThreadPoolBuilder::new()
+		.num_threads(threads.get())
+		.build()?;
+	let table = score_table(score);
+
+	Ok(pool.install(|| {
+		lines
+			.par_iter()
+			.filter_map(|s| parse_round(s))
+			.map(|(p1, p2)| u32::from(table[usize::from(p1)][usize::from(p2)]))
+			.sum()
+	}))
+}
+
+/// Score every round in a strategy guide as both a shape (as in [`score_shape`]) and an outcome (as in [`score_win`]) in a single pass.
+/// Returns the total of each, and how much the outcome interpretation scores over the shape interpretation.
+fn both_scores<T: Iterator<Item = String>>(lines: T) -> (u32, u32, i64) {
+	let (shape_table, win_table) = (score_table(score_shape), score_table(score_win));
+	let (shape, win) = lines
+		.filter_map(|s| parse_round(&s))
+		.map(|(p1, p2)| (usize::from(p1), usize::from(p2)))
+		.fold((0, 0), |(shape, win), (p1, p2)| {
+			(
+				shape + u32::from(shape_table[p1][p2]),
+				win + u32::from(win_table[p1][p2]),
+			)
+		});
+
+	(shape, win, i64::from(win) - i64::from(shape))
+}
+
+/// Score every round of a strategy guide made only of the opponent's shapes (as 0-based inputs as in [`score_shape`]),
+/// choosing our shape in each round with `strategy`, and sum them up
+fn fixed_score<T: Iterator<Item = u8>>(opponent: T, strategy: Strategy) -> u32 {
+	opponent
+		// Remember what the opponent played last round, for strategies which respond to it
+		.scan(None, |previous, p1| {
+			let p2 = strategy.shape(p1, *previous);
+			*previous = Some(p1);
+
+			Some(u32::from(score_shape(p1, p2)))
+		})
+		.sum()
+}
+
+/// Score rounds as they're read from `reader` (such as stdin), writing the total score so far to `out` after each one.
+/// Blank lines are skipped, and lines which aren't rounds are reported without changing the total. Returns the final total.
+fn replay<R: BufRead, W: Write>(
+	reader: R,
+	out: &mut W,
+	score: fn(u8, u8) -> u8,
+	letters: &Letters,
+) -> Result<u32> {
+	let mut total = 0;
+
+	for line in reader.lines() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		match parse_round(&letters.translate(&line)) {
+			Some((p1, p2)) => {
+				total += u32::from(score(p1, p2));
+				writeln!(out, "{total}")?;
+			}
+			None => writeln!(out, "`{}` is not a round", line.trim())?,
+		}
+		// Show the total straight away, rather than when the output buffer fills up
+		out.flush()?;
+	}
+
+	Ok(total)
+}
+
+/// How to score each round, and how to tell how each round turned out
+type Scoring = (fn(u8, u8) -> u8, fn(u8, u8) -> RoundOutcome);
+
+/// The [`Scoring`] of a mode, or `None` for both and fixed mode, which have their own scoring
+fn scoring(mode: &Mode) -> Option<Scoring> {
+	match mode {
+		Mode::Shape => Some((score_shape, RoundOutcome::shape)),
+		Mode::Win => Some((score_win, RoundOutcome::win)),
+		Mode::Optimal => Some((score_optimal, RoundOutcome::optimal)),
+		Mode::Both | Mode::Fixed => None,
+	}
+}
+
+/// Write a random strategy guide with `rounds` rounds. The opponent's shape is chosen uniformly,
+/// and the outcome of each round (if the second letter is a shape) is chosen according to `weights`, which are the
+/// relative weights of losing, drawing, and winning.
+fn generate<W: Write, R: Rng>(
+	out: &mut W,
+	rng: &mut R,
+	rounds: usize,
+	weights: &[u32],
+) -> Result<()> {
+	let outcomes = WeightedIndex::new(weights)?;
+
+	for _ in 0..rounds {
+		let p1 = rng.gen_range(0..3);
+		// 0 - lose, 1 - tie, 2 - win, as in score_win
+		let outcome = u8::try_from(outcomes.sample(rng))?;
+		// The shape which gets that outcome. Same logic as score_win, but adding 2 instead of subtracting 1 to stay positive
+		let p2 = (p1 + outcome + 2) % 3;
+
+		writeln!(out, "{} {}", char::from(b'A' + p1), char::from(b'X' + p2))?;
+	}
+
+	Ok(())
+}
+
+/// Print the total score of a strategy guide, followed by a table of the outcome counts from [`histogram`]
+fn print_histogram(score: u32, counts: [[u32; 3]; 3]) {
+	println!("{score}");
+
+	println!("{:>10}{:>6}{:>6}{:>6}", "", "Lose", "Draw", "Win");
+	for (shape, counts) in ["Rock", "Paper", "Scissors"].iter().zip(counts) {
+		println!(
+			"{shape:>10}{:>6}{:>6}{:>6}",
+			counts[0], counts[1], counts[2]
+		);
+	}
+}
+
 fn main() -> Result<()> {
 	let args = Args::parse();
 
-	// Load input file, make sure it's openable
-	let file = File::open(args.input_file)?;
+	if let Some(Command::Generate {
+		output_file,
+		rounds,
+		weights,
+		seed,
+	}) = args.command
+	{
+		let mut rng = StdRng::seed_from_u64(seed);
+		let mut out: Box<dyn Write> = match output_file {
+			Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+			None => Box::new(io::BufWriter::new(io::stdout().lock())),
+		};
+
+		return generate(&mut out, &mut rng, rounds, &weights);
+	}
 
-	// Switch the scoring mode based on arguments
-	let score = match args.mode {
-		Mode::Shape => score_shape,
-		Mode::Win => score_win,
+	let letters = Letters {
+		opponent: args.opponent_letters,
+		own: args.self_letters,
 	};
+	// Mode is only optional when generating, so it must be present here.
+	let mode = args.mode.unwrap();
+
+	if args.interactive {
+		let (score, _) = scoring(&mode).ok_or_else(|| {
+			anyhow!("Both and fixed mode score a whole file, so they can't be used interactively")
+		})?;
+		replay(
+			io::stdin().lock(),
+			&mut io::stdout().lock(),
+			score,
+			&letters,
+		)?;
+
+		return Ok(());
+	}
+
+	// Load input file, make sure it's openable
+	let file = File::open(args.input_file)?;
 
 	// Read lines from file
-	let total_score: u32 = io::BufReader::new(file)
+	let lines = io::BufReader::new(file)
 		.lines()
 		// Skip lines which couldn't be read
-		.flatten()
-		// Convert letters into 0-based inputs as expected by score_ functions,
-		// and then convert to scores depending on chosen scoring method
-		.map(|s| {
-			let b = s.as_bytes();
-			u32::from(score(b[0] - b'A', b[2] - b'X'))
-		})
-		// Then sum up the scores
-		.sum();
+		.flatten();
 
-	println!("{total_score}");
+	if let Mode::Fixed = mode {
+		// Skipping lines which aren't one of the opponent's shapes
+		let opponent = lines.filter_map(|line| letters.opponent(&line));
+		// Clap makes sure there's a strategy in fixed mode
+		println!("{}", fixed_score(opponent, args.strategy.unwrap()));
+
+		return Ok(());
+	}
+
+	// Convert from whatever letters the strategy guide uses into the usual ones
+	let lines = lines.map(|line| letters.translate(&line));
+
+	if let Mode::Both = mode {
+		let (shape, win, difference) = both_scores(lines);
+
+		println!("Shape: {shape}");
+		println!("Win: {win}");
+		println!("Difference: {difference}");
+
+		return Ok(());
+	}
+
+	// Switch the scoring mode based on arguments, along with how to tell how each round turned out to match it.
+	// Both and fixed mode have their own scoring, and have already returned.
+	let (score, outcome) = scoring(&mode).unwrap();
+
+	if args.histogram {
+		// The histogram needs to go through the lines as well, so keep them around to go through twice
+		let lines = lines.collect::<Vec<_>>();
+
+		let total = match args.threads {
+			Some(threads) => par_total_score(&lines, score, threads)?,
+			None => total_score(lines.iter().cloned(), score),
+		};
+		let counts = histogram(lines.into_iter(), outcome);
+
+		match args.format {
+			Format::Plain => print_histogram(total, counts),
+			Format::Json => println!("{}", serde_json::to_string(&Report::new(total, counts))?),
+		}
+	} else if let Some(threads) = args.threads {
+		// Rounds are handed out to threads from memory, so read all of them first
+		let lines = lines.collect::<Vec<_>>();
+
+		println!("{}", par_total_score(&lines, score, threads)?);
+	} else {
+		println!("{}", total_score(lines, score));
+	}
 
 	Ok(())
 }
@@ -110,4 +624,251 @@ mod tests {
 		assert_eq!(score_win(b'B' - b'A', b'X' - b'X'), 1);
 		assert_eq!(score_win(b'C' - b'A', b'Z' - b'X'), 7);
 	}
+
+	#[test]
+	fn test_optimal() {
+		// Example given by page, where winning every round gives 8 + 9 + 7
+		let example = ["A Y", "B X", "C Z"].map(str::to_string);
+		assert_eq!(total_score(example.into_iter(), score_optimal), 24);
+
+		// Exactly one of our shapes gets the optimal score against each shape, so there's no tie to break
+		for p1 in 0..3 {
+			let optimal = score_optimal(p1, 0);
+			assert_eq!(
+				(0..3).filter(|p2| score_shape(p1, *p2) == optimal).count(),
+				1
+			);
+			assert!((0..3).all(|p2| score_shape(p1, p2) <= optimal));
+		}
+	}
+
+	#[test]
+	fn test_both() {
+		let example = ["A Y", "B X", "C Z"].map(str::to_string);
+		assert_eq!(both_scores(example.into_iter()), (15, 12, -3));
+	}
+
+	#[test]
+	fn test_short_lines() {
+		// No trailing newline
+		let example = "A Y\nB X\nC Z";
+		assert_eq!(
+			total_score(example.lines().map(str::to_string), score_shape),
+			15
+		);
+
+		// Trailing newline, then a final empty line
+		let example = "A Y\nB X\nC Z\n\n";
+		assert_eq!(
+			total_score(example.lines().map(str::to_string), score_shape),
+			15
+		);
+		assert_eq!(
+			total_score(example.lines().map(str::to_string), score_win),
+			12
+		);
+
+		// Final line cut short
+		let example = "A Y\nB X\nC Z\nA";
+		assert_eq!(
+			total_score(example.lines().map(str::to_string), score_shape),
+			15
+		);
+	}
+
+	#[test]
+	fn test_letters() {
+		let letters = Letters {
+			opponent: parse_letters("123").unwrap(),
+			own: parse_letters("LDW").unwrap(),
+		};
+		let example = ["1 D", "2 L", "3 W", "A Y"].map(|line| letters.translate(line));
+
+		// The last line uses the usual letters, which aren't rounds with these letters
+		assert_eq!(example, ["A Y", "B X", "C Z", ""]);
+		assert_eq!(total_score(example.iter().cloned(), score_shape), 15);
+		assert_eq!(total_score(example.into_iter(), score_win), 12);
+
+		assert!(parse_letters("AB").is_err());
+		assert!(parse_letters("ABA").is_err());
+		assert!(parse_letters("A C").is_err());
+	}
+
+	#[test]
+	fn test_replay() {
+		let letters = Letters {
+			opponent: ['A', 'B', 'C'],
+			own: ['X', 'Y', 'Z'],
+		};
+		let mut out = Vec::new();
+
+		let total = replay(
+			std::io::Cursor::new("A Y\nB X\n\nC Z\n"),
+			&mut out,
+			score_shape,
+			&letters,
+		)
+		.unwrap();
+
+		assert_eq!(total, 15);
+		assert_eq!(String::from_utf8(out).unwrap(), "8\n9\n15\n");
+
+		let mut out = Vec::new();
+		replay(
+			std::io::Cursor::new("A Y\nrock\n"),
+			&mut out,
+			score_win,
+			&letters,
+		)
+		.unwrap();
+		assert_eq!(
+			String::from_utf8(out).unwrap(),
+			"4\n`rock` is not a round\n"
+		);
+	}
+
+	#[test]
+	fn test_histogram() {
+		let example = || ["A Y", "B X", "C Z"].map(str::to_string).into_iter();
+
+		assert_eq!(
+			histogram(example(), RoundOutcome::shape),
+			[[0, 0, 1], [1, 0, 0], [0, 1, 0]]
+		);
+		assert_eq!(
+			histogram(example(), RoundOutcome::win),
+			[[0, 1, 0], [1, 0, 0], [0, 0, 1]]
+		);
+		assert_eq!(
+			histogram(example(), RoundOutcome::optimal),
+			[[0, 0, 1], [0, 0, 1], [0, 0, 1]]
+		);
+	}
+
+	#[test]
+	fn test_json_report() {
+		let example = ["A Y", "A X", "B X", "C Z"].map(str::to_string).into_iter();
+		let report = Report::new(
+			total_score(example.clone(), score_shape),
+			histogram(example, RoundOutcome::shape),
+		);
+
+		let json = serde_json::to_string(&report).unwrap();
+		let report: Report = serde_json::from_str(&json).unwrap();
+
+		assert_eq!(report.score, 8 + 4 + 1 + 6);
+		let rock = &report.shapes[0];
+		assert_eq!(rock.shape, "rock");
+		assert_eq!((rock.rounds, rock.lose, rock.draw, rock.win), (2, 0, 1, 1));
+		// Half of the rounds against rock were won
+		assert!((rock.win_rate - 0.5).abs() < f64::EPSILON);
+		assert!((report.shapes[1].lose_rate - 1.0).abs() < f64::EPSILON);
+		// Rates are still numbers when there weren't any rounds
+		let report = Report::new(0, [[0; 3]; 3]);
+		assert!(report.shapes[2].win_rate.abs() < f64::EPSILON);
+	}
+
+	#[test]
+	fn test_score_table() {
+		for score in [score_shape, score_win, score_optimal] {
+			let table = score_table(score);
+
+			for p1 in 0..3 {
+				for p2 in 0..3 {
+					assert_eq!(table[usize::from(p1)][usize::from(p2)], score(p1, p2));
+				}
+			}
+		}
+
+		// Rock against each of our shapes, as in score_shape
+		assert_eq!(score_table(score_shape)[0], [4, 8, 3]);
+	}
+
+	#[test]
+	fn test_fixed() {
+		let letters = Letters {
+			opponent: ['A', 'B', 'C'],
+			own: ['X', 'Y', 'Z'],
+		};
+		// The opponent's column of the example
+		let opponent = || {
+			["A", "B", "C"]
+				.iter()
+				.filter_map(|line| letters.opponent(line))
+		};
+
+		// Winning every round, the same as optimal mode
+		assert_eq!(fixed_score(opponent(), Strategy::Win), 8 + 9 + 7);
+		assert_eq!(
+			fixed_score(opponent(), Strategy::Win),
+			total_score(
+				["A Y", "B X", "C Z"].map(str::to_string).into_iter(),
+				score_optimal
+			)
+		);
+		assert_eq!(fixed_score(opponent(), Strategy::Lose), 3 + 1 + 2);
+		assert_eq!(fixed_score(opponent(), Strategy::Draw), 4 + 5 + 6);
+		// Rock against rock, then rock against paper, then paper against scissors
+		assert_eq!(fixed_score(opponent(), Strategy::Mirror), 4 + 1 + 2);
+		// Rock against rock, then paper against paper, then scissors against scissors
+		assert_eq!(fixed_score(opponent(), Strategy::Counter), 4 + 5 + 6);
+
+		assert_eq!(letters.opponent("A Y"), None);
+		assert_eq!(letters.opponent(" C "), Some(2));
+	}
+
+	#[test]
+	fn test_generate() {
+		let mut rng = StdRng::seed_from_u64(1234);
+		let mut out = Vec::new();
+		generate(&mut out, &mut rng, 1000, &[1, 1, 1]).unwrap();
+		let lines = String::from_utf8(out).unwrap();
+
+		assert_eq!(lines.lines().count(), 1000);
+
+		// Every generated line is a round, so none of them are skipped when scoring
+		let rounds = lines
+			.lines()
+			.map(|line| parse_round(line).unwrap())
+			.collect::<Vec<_>>();
+		for score in [score_shape, score_win] {
+			assert_eq!(
+				total_score(lines.lines().map(str::to_string), score),
+				rounds
+					.iter()
+					.map(|(p1, p2)| u32::from(score(*p1, *p2)))
+					.sum::<u32>()
+			);
+		}
+
+		// Only winning rounds should score at least 7 each
+		let mut out = Vec::new();
+		generate(&mut out, &mut rng, 1000, &[0, 0, 1]).unwrap();
+		let lines = String::from_utf8(out).unwrap();
+
+		for line in lines.lines() {
+			assert!(total_score(std::iter::once(line.to_string()), score_shape) >= 7);
+		}
+	}
+
+	#[test]
+	fn test_parallel() {
+		let mut rng = StdRng::seed_from_u64(1234);
+		let mut out = Vec::new();
+		generate(&mut out, &mut rng, 10_000, &[1, 2, 3]).unwrap();
+		let lines = String::from_utf8(out)
+			.unwrap()
+			.lines()
+			.map(str::to_string)
+			.collect::<Vec<_>>();
+
+		for score in [score_shape, score_win, score_optimal] {
+			let serial = total_score(lines.iter().cloned(), score);
+
+			for threads in [1, 4] {
+				let threads = NonZeroUsize::new(threads).unwrap();
+				assert_eq!(par_total_score(&lines, score, threads).unwrap(), serial);
+			}
+		}
+	}
 }
@@ -2,13 +2,16 @@
 #![feature(array_methods)]
 #![deny(clippy::pedantic)]
 use std::{
-	error::Error,
+	collections::HashSet,
 	fs::File,
-	io::{self, BufRead},
+	hash::Hash,
+	io::{self, BufRead, Write},
 	path::PathBuf,
 };
 
+use anyhow::{anyhow, Result};
 use clap::{Parser, ValueEnum};
+use rayon::prelude::{ParallelBridge, ParallelIterator};
 
 #[derive(Clone, ValueEnum)]
 enum Mode {
@@ -16,6 +19,35 @@ enum Mode {
 	Single,
 	/// The second variant of the problem, where three rucksacks are searched for a common item
 	Triple,
+	/// Both variants of the problem at once, reading the input file only once
+	Both,
+	/// Find the item which is common to every rucksack in the file
+	Common,
+	/// Treat each whole line as one sack, and sum the priorities of every type of item which appears in it more than once
+	Duplicates,
+	/// Count every type of item across all of the rucksacks in the file, and find the least common type(s) of item
+	Rarest,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Algo {
+	/// Sort each sack and walk through them together
+	Merge,
+	/// Intersect a hash set of the items in each sack
+	Set,
+	/// Intersect a bit-set of the items in each sack, with one bit per letter
+	Bits,
+}
+
+impl Algo {
+	/// Find the common item between sacks using this algorithm
+	fn common_item<const NUM_SACKS: usize>(self, sacks: [&[u8]; NUM_SACKS]) -> Result<u8> {
+		match self {
+			Algo::Merge => get_common_item(sacks),
+			Algo::Set => get_common_item_set(sacks),
+			Algo::Bits => get_common_item_bits(sacks),
+		}
+	}
 }
 
 #[derive(Parser)]
@@ -26,10 +58,60 @@ struct Args {
 	/// What mode to run the program in
 	#[arg(value_enum)]
 	mode: Mode,
+	/// Which algorithm to use to find the common item between sacks
+	#[arg(long, value_enum, default_value_t = Algo::Merge)]
+	algo: Algo,
+	/// Find the common items of different sacks (or groups of sacks) on multiple threads at once
+	#[arg(long)]
+	parallel: bool,
+	#[command(flatten)]
+	report: Report,
+	/// How many sacks are in each group, for the triple and both modes.
+	/// Groups of any size other than 3 always find their common item with a bit-set, whatever the algorithm
+	#[arg(long, value_name = "N", default_value_t = 3)]
+	group_size: usize,
+	/// Don't check that every line is ascii before looking for items. Items are single bytes,
+	/// so any other characters are split apart into meaningless items
+	#[arg(long)]
+	no_ascii_check: bool,
+	/// Only count the first of any identical rucksacks, skipping lines which have already been seen.
+	/// Sacks are grouped after skipping repeats, in the triple and both modes
+	#[arg(long)]
+	dedupe: bool,
 }
 
-/// Find the common item (character) from among `NUM_SACKS` different collections of ascii characters
-fn get_common_item<const NUM_SACKS: usize>(sacks: [&[u8]; NUM_SACKS]) -> u8 {
+/// What to print about each sack (or group of sacks) along with the total
+#[derive(clap::Args)]
+struct Report {
+	/// Also print the common item of every sack (or group of sacks), in the same order as the input file
+	#[arg(long)]
+	verbose: bool,
+	/// Also print which sack of each line (or group) the common item was found in, and its position in that sack once sorted.
+	/// Only for the single and triple modes, with groups of 3, and always uses the merge algorithm
+	#[arg(long)]
+	which: bool,
+}
+
+/// Where the walk through the sacks of [`get_common_item`] found the common item
+#[derive(Debug, PartialEq, Eq)]
+struct Found {
+	item: u8,
+	/// The (0-based) index of the sack which was walked onto the common item last.
+	/// If every sack starts with the common item once sorted, this is the first sack.
+	sack: usize,
+	/// The (0-based) position of the common item in that sack, once sorted
+	position: usize,
+}
+
+/// Find the common item (character) from among `NUM_SACKS` different collections of ascii characters.
+/// Fails if there is no common item, such as when any of the sacks are empty. If more than one item is common to every sack,
+/// the one with the smallest ascii value is found (so uppercase letters come before lowercase letters), as with every [`Algo`].
+fn get_common_item<const NUM_SACKS: usize>(sacks: [&[u8]; NUM_SACKS]) -> Result<u8> {
+	get_common_item_found(sacks).map(|found| found.item)
+}
+
+/// The same as [`get_common_item`], but also reports where the common item was found
+fn get_common_item_found<const NUM_SACKS: usize>(sacks: [&[u8]; NUM_SACKS]) -> Result<Found> {
 	// Create a copy of each of the sacs so that we can sort them
 	let mut sacks = sacks.map(<[u8]>::to_vec);
 	for sack in sacks.iter_mut() {
@@ -38,7 +120,13 @@ fn get_common_item<const NUM_SACKS: usize>(sacks: [&[u8]; NUM_SACKS]) -> u8 {
 
 	// Create an iterator for each sack to walk through that sack. `sack_tops` are the next item under consideration
 	let mut sack_iters = sacks.map(std::iter::IntoIterator::into_iter);
-	let mut sack_tops = sack_iters.clone().map(|mut iter| iter.next().unwrap());
+	let mut sack_tops = [0; NUM_SACKS];
+	for (top, iter) in sack_tops.iter_mut().zip(sack_iters.iter_mut()) {
+		*top = iter.next().ok_or_else(|| anyhow!("Empty sack"))?;
+	}
+	// How far through each sack its iterator is, and which sack was iterated last
+	let mut positions = [0; NUM_SACKS];
+	let mut last = 0;
 
 	// Loop through all of the sacks, checking for matching characters. Each loop iterates only one iterator from a sack at a time.
 	loop {
@@ -63,13 +151,21 @@ fn get_common_item<const NUM_SACKS: usize>(sacks: [&[u8]; NUM_SACKS]) -> u8 {
 		) {
 			// If the accumulation operation returns Ok, then that means everything was identical and we
 			// found the common element between the sacks - return it
-			Ok(acc) => return *acc,
+			Ok(acc) => {
+				return Ok(Found {
+					item: *acc,
+					sack: last,
+					position: positions[last],
+				})
+			}
 			// Otherwise, we need to keep searching for the common element. The accumulation returns which sack has the smallest currently considered
 			// value, so we iterate that sack and look at the next value. Since all of the sacks are sorted and we only iterate the sack with the
 			// smallest considered value, we know that this value can't be common between the sacks.
 			Err((i, _)) => {
 				if let Some(top) = sack_iters[i].next() {
 					sack_tops[i] = top;
+					positions[i] += 1;
+					last = i;
 				} else {
 					// If there aren't any more items in the sacks, then we failed to find the common item between the sacks.
 					// Break the loop
@@ -79,35 +175,451 @@ fn get_common_item<const NUM_SACKS: usize>(sacks: [&[u8]; NUM_SACKS]) -> u8 {
 		}
 	}
 
-	// There should always be a common item between the sacks, so this is unreachable
-	unreachable!()
+	Err(anyhow!("No item is common to every sack"))
+}
+
+/// The same as [`get_common_item`], but by intersecting the set of items in each sack
+fn get_common_item_set<const NUM_SACKS: usize>(sacks: [&[u8]; NUM_SACKS]) -> Result<u8> {
+	sacks
+		.iter()
+		.map(|sack| sack.iter().copied().collect::<HashSet<_>>())
+		.reduce(|acc, set| &acc & &set)
+		// Sets aren't in any particular order, so choose between multiple common items the same way as get_common_item
+		.and_then(|set| set.into_iter().min())
+		.ok_or_else(|| anyhow!("No item is common to every sack"))
 }
 
-/// Split a single string into multiple substrings of equal size
-fn split_sacks<const NUM_SACKS: usize>(string: &[u8]) -> [&[u8]; NUM_SACKS] {
+/// The same as [`get_common_item`], but each sack is converted into a bit-set with one bit per letter,
+/// so that intersecting them is just a bitwise AND. Items must be ascii letters.
+fn get_common_item_bits<const NUM_SACKS: usize>(sacks: [&[u8]; NUM_SACKS]) -> Result<u8> {
+	// a-z -> 0..26, A-Z -> 26..52, which conveniently is one less than the priority
+	let common = sacks
+		.iter()
+		.map(|sack| {
+			sack.iter()
+				.try_fold(0u64, |set, item| Ok(set | (1 << (priority(*item)? - 1))))
+		})
+		.try_fold(u64::MAX, |acc, set: Result<u64>| set.map(|set| acc & set))?;
+
+	if common == 0 {
+		return Err(anyhow!("No item is common to every sack"));
+	}
+
+	// There should be exactly one common item between the sacks, so there should be a single bit set.
+	// If there's more than one, choose the same way as get_common_item - uppercase letters have smaller ascii values,
+	// so take the lowest uppercase bit if there is one. Then convert it back to a letter
+	let upper = common >> 26;
+	let bit = u8::try_from(if upper == 0 {
+		common.trailing_zeros()
+	} else {
+		26 + upper.trailing_zeros()
+	})?;
+	Ok(if bit < 26 {
+		b'a' + bit
+	} else {
+		b'A' + bit - 26
+	})
+}
+
+/// Find the items (characters) common to every one of an arbitrary number of sacks, in ascending order.
+/// Unlike [`get_common_item`], there may be any number of common items (including none), and the sacks may be streamed.
+fn common_items<S: AsRef<[u8]>, T: Iterator<Item = S>>(sacks: T) -> Vec<u8> {
+	sacks
+		// Convert each sack into a set of the (ascii) items it contains, one bit per item
+		.map(|sack| {
+			sack.as_ref()
+				.iter()
+				.filter(|item| item.is_ascii())
+				.fold(0u128, |set, item| set | (1 << item))
+		})
+		// Intersect the sets of every sack. There are no common items if there are no sacks at all.
+		.reduce(|acc, set| acc & set)
+		.map_or_else(Vec::new, |set| {
+			(0..128u8).filter(|item| set & (1 << item) != 0).collect()
+		})
+}
+
+/// The sum of the priorities of every type of item which appears more than once in a single sack.
+/// Each duplicated type of item is only counted once, no matter how many times it appears. Items must be ascii letters.
+fn duplicate_priority(sack: &[u8]) -> Result<u64> {
+	// Bit-sets of items as in get_common_item_bits - items seen at least once, and items seen more than once
+	let (_, duplicates) = sack
+		.iter()
+		.try_fold((0u64, 0u64), |(seen, duplicates), item| {
+			let bit = 1 << (priority(*item)? - 1);
+			Ok::<_, anyhow::Error>((seen | bit, duplicates | (seen & bit)))
+		})?;
+
+	// Bit i is set for the item with priority i + 1
+	Ok((0..52)
+		.filter(|i| duplicates & (1 << i) != 0)
+		.map(|i| i + 1)
+		.sum())
+}
+
+/// Sum the priorities of the duplicated items in each sack, as in [`duplicate_priority`]
+fn duplicate_priorities<S: AsRef<[u8]>, T: Iterator<Item = S>>(lines: T) -> Result<u64> {
+	lines.map(|sack| duplicate_priority(sack.as_ref())).sum()
+}
+
+/// Count how many times every type of item appears across all of the sacks, and find the least common types of item
+/// along with how many times they appear. Types of item which don't appear at all aren't counted, so there are no rarest items
+/// if there are no items. Items must be ascii letters.
+fn rarest_items<S: AsRef<[u8]>, T: Iterator<Item = S>>(lines: T) -> Result<(u64, Vec<u8>)> {
+	// Indexed by priority - 1, as in duplicate_priority
+	let mut counts = [0u64; 52];
+	for (i, sack) in lines.enumerate() {
+		for item in sack.as_ref() {
+			let priority = priority(*item).map_err(|err| err.context(format!("Sack {}", i + 1)))?;
+			counts[usize::from(priority - 1)] += 1;
+		}
+	}
+
+	let rarest = counts.iter().copied().filter(|count| *count > 0).min();
+
+	Ok(match rarest {
+		Some(rarest) => (
+			rarest,
+			(b'a'..=b'z')
+				.chain(b'A'..=b'Z')
+				.zip(counts)
+				.filter(|(_, count)| *count == rarest)
+				.map(|(item, _)| item)
+				.collect(),
+		),
+		None => (0, Vec::new()),
+	})
+}
+
+/// Split a single string into multiple substrings of equal size.
+/// Fails if the string can't be split evenly.
+fn split_sacks<const NUM_SACKS: usize>(string: &[u8]) -> Result<[&[u8]; NUM_SACKS]> {
 	let size = string.len() / NUM_SACKS;
+	if size * NUM_SACKS != string.len() {
+		return Err(anyhow!(
+			"A sack of {} items can't be split into {NUM_SACKS} equal compartments",
+			string.len()
+		));
+	}
 
-	(0..NUM_SACKS)
-		.map(|i| &string[(i * size)..((i + 1) * size)])
-		// I wish there was a try_collect
-		.collect::<Vec<_>>()
-		.try_into()
-		.unwrap()
+	let mut sacks = [&string[..0]; NUM_SACKS];
+	for (i, sack) in sacks.iter_mut().enumerate() {
+		*sack = &string[(i * size)..((i + 1) * size)];
+	}
+
+	Ok(sacks)
 }
 
-/// Convert an item to a priority
-fn priority(item: u8) -> u8 {
-	if item <= b'Z' {
-		item - b'A' + 27
+/// Make sure the `i`th (0-based) line is made only of ascii characters, so that each item is a whole character
+fn check_ascii(i: usize, line: &[u8]) -> Result<()> {
+	if line.is_ascii() {
+		Ok(())
 	} else {
-		item - b'a' + 1
+		Err(anyhow!(
+			"Line {} is not ascii: `{}`",
+			i + 1,
+			String::from_utf8_lossy(line)
+		))
+	}
+}
+
+/// Check that each line is ascii as it's read, as in [`check_ascii`]. The first line which isn't ends the lines early,
+/// and its error is put in `not_ascii`.
+fn ascii_lines<'a, T: Iterator<Item = Vec<u8>> + 'a>(
+	lines: T,
+	not_ascii: &'a mut Option<anyhow::Error>,
+) -> impl Iterator<Item = Vec<u8>> + 'a {
+	lines
+		.enumerate()
+		.map_while(|(i, line)| match check_ascii(i, &line) {
+			Ok(()) => Some(line),
+			Err(err) => {
+				*not_ascii = Some(err);
+				None
+			}
+		})
+}
+
+/// Skip any sacks which are identical to a sack which has already been seen, keeping the rest in order
+fn dedupe<S: AsRef<[u8]> + Clone + Eq + Hash, T: Iterator<Item = S>>(
+	lines: T,
+) -> impl Iterator<Item = S> {
+	let mut seen = HashSet::new();
+
+	lines.filter(move |line| seen.insert(line.clone()))
+}
+
+/// Convert an item to a priority. Fails if the item isn't an ascii letter.
+fn priority(item: u8) -> Result<u8> {
+	match item {
+		b'a'..=b'z' => Ok(item - b'a' + 1),
+		b'A'..=b'Z' => Ok(item - b'A' + 27),
+		_ => Err(anyhow!("`{}` is not an item", char::from(item))),
 	}
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+/// Sum the priorities of the common items between the two halves of each sack
+fn single_priorities<S: AsRef<[u8]>, T: Iterator<Item = S>>(lines: T, algo: Algo) -> Result<u64> {
+	lines
+		.map(|sack| {
+			let item = algo.common_item(split_sacks::<2>(sack.as_ref())?)?;
+			Ok(u64::from(priority(item)?))
+		})
+		.sum()
+}
+
+/// Sum the priorities of the common items between each group of three sacks
+fn triple_priorities<S: AsRef<[u8]>, T: Iterator<Item = S>>(lines: T, algo: Algo) -> Result<u64> {
+	lines
+		.array_chunks::<3>()
+		// Annoying type conversions
+		.map(|sacks| {
+			let item = algo.common_item(sacks.each_ref().map(AsRef::as_ref))?;
+			Ok(u64::from(priority(item)?))
+		})
+		.sum()
+}
+
+/// Make sure that `num_sacks` sacks can be split evenly into groups of `group_size`, with no sacks left over
+fn check_groups(num_sacks: usize, group_size: usize) -> Result<()> {
+	if group_size == 0 {
+		return Err(anyhow!("Groups must contain at least one sack"));
+	}
+
+	let leftover = num_sacks % group_size;
+	if leftover != 0 {
+		return Err(anyhow!(
+			"{num_sacks} lines can't be split into groups of {group_size}, leaving {leftover} leftover lines"
+		));
+	}
+
+	Ok(())
+}
+
+/// The smallest item common to every sack in a group of any size. Fails if there is no common item.
+fn group_common_item<S: AsRef<[u8]>>(group: &[S]) -> Result<u8> {
+	common_items(group.iter())
+		.first()
+		.copied()
+		.ok_or_else(|| anyhow!("No item is common to every sack"))
+}
+
+/// Sum the priorities of the common items between each group of `group_size` sacks.
+/// Fails if the sacks can't be split evenly into groups.
+fn group_priorities<S: AsRef<[u8]>>(lines: &[S], group_size: usize, algo: Algo) -> Result<u64> {
+	check_groups(lines.len(), group_size)?;
+
+	// Groups of three have their size known at compile time, so can use any algorithm
+	if group_size == 3 {
+		return triple_priorities(lines.iter(), algo);
+	}
+
+	lines
+		.chunks(group_size)
+		.map(|group| Ok(u64::from(priority(group_common_item(group)?)?)))
+		.sum()
+}
+
+/// Find the common item of each of `inputs` (either sacks or groups of sacks) with `find`, as pairs of the (0-based) index
+/// of each input and its common item. If `parallel`, inputs are handed out to threads as they're read.
+fn indexed_items<I: Send, O: Send, T: Iterator<Item = I> + Send>(
+	inputs: T,
+	parallel: bool,
+	find: impl Fn(I) -> Result<O> + Send + Sync,
+) -> Result<Vec<(usize, O)>> {
+	let inputs = inputs.enumerate();
+	// Say which input failed, counting from 1
+	let find = |(i, input)| {
+		find(input)
+			.map(|item| (i, item))
+			.map_err(|err| err.context(format!("Sack (or group) {}", i + 1)))
+	};
+
+	let mut items = if parallel {
+		inputs.par_bridge().map(find).collect::<Result<Vec<_>>>()?
+	} else {
+		inputs.map(find).collect::<Result<Vec<_>>>()?
+	};
+
+	// Threads can finish in any order, so put the items back in the order of the inputs
+	items.sort_unstable_by_key(|(i, _)| *i);
+
+	Ok(items)
+}
+
+/// The common item between the two halves of each sack, by line
+fn single_items<S: AsRef<[u8]> + Send, T: Iterator<Item = S> + Send>(
+	lines: T,
+	algo: Algo,
+	parallel: bool,
+) -> Result<Vec<(usize, u8)>> {
+	indexed_items(lines, parallel, |sack| {
+		algo.common_item(split_sacks::<2>(sack.as_ref())?)
+	})
+}
+
+/// The common item between each group of three sacks, by group
+fn triple_items<S: AsRef<[u8]> + Send, T: Iterator<Item = S> + Send>(
+	lines: T,
+	algo: Algo,
+	parallel: bool,
+) -> Result<Vec<(usize, u8)>> {
+	indexed_items(lines.array_chunks::<3>(), parallel, |sacks| {
+		algo.common_item(sacks.each_ref().map(AsRef::as_ref))
+	})
+}
+
+/// The common item between each group of `group_size` sacks, by group.
+/// Fails if the sacks can't be split evenly into groups.
+fn group_items<S: AsRef<[u8]> + Sync>(
+	lines: &[S],
+	group_size: usize,
+	algo: Algo,
+	parallel: bool,
+) -> Result<Vec<(usize, u8)>> {
+	check_groups(lines.len(), group_size)?;
+
+	if group_size == 3 {
+		triple_items(lines.iter(), algo, parallel)
+	} else {
+		indexed_items(lines.chunks(group_size), parallel, group_common_item)
+	}
+}
+
+/// Where the common item of each line (in single mode) or group of three lines (in triple mode) was found, by line or group
+fn which_items<S: AsRef<[u8]> + Send, T: Iterator<Item = S> + Send>(
+	lines: T,
+	mode: &Mode,
+	group_size: usize,
+	parallel: bool,
+) -> Result<Vec<(usize, Found)>> {
+	match mode {
+		Mode::Single => indexed_items(lines, parallel, |sack| {
+			get_common_item_found(split_sacks::<2>(sack.as_ref())?)
+		}),
+		Mode::Triple if group_size == 3 => {
+			// Count the lines first, as in group_items
+			let lines = lines.collect::<Vec<_>>();
+			check_groups(lines.len(), group_size)?;
+
+			indexed_items(lines.into_iter().array_chunks::<3>(), parallel, |sacks| {
+				get_common_item_found(sacks.each_ref().map(AsRef::as_ref))
+			})
+		}
+		Mode::Triple => Err(anyhow!("--which only works with groups of 3")),
+		_ => Err(anyhow!("--which only works in single or triple mode")),
+	}
+}
+
+/// Find the common items of the sacks in `lines` depending on the mode, and write the results to `out`
+fn run<W: Write>(
+	out: &mut W,
+	args: &Args,
+	lines: impl Iterator<Item = Vec<u8>> + Send,
+) -> Result<()> {
+	let lines: Box<dyn Iterator<Item = Vec<u8>> + Send> = if args.dedupe {
+		Box::new(dedupe(lines))
+	} else {
+		Box::new(lines)
+	};
+
+	// Find common items (either in halves of a sack or between multiple sacks) depending on mode,
+	// then convert them into priorities and sum
+	match args.mode {
+		Mode::Single | Mode::Triple => {
+			let items = if args.report.which {
+				let found = which_items(lines, &args.mode, args.group_size, args.parallel)?;
+
+				for (i, found) in &found {
+					writeln!(
+						out,
+						"{}: {} found in sack {} at position {}",
+						i + 1,
+						char::from(found.item),
+						found.sack + 1,
+						found.position + 1
+					)?;
+				}
+
+				found
+					.into_iter()
+					.map(|(i, found)| (i, found.item))
+					.collect()
+			} else if let Mode::Single = args.mode {
+				single_items(lines, args.algo, args.parallel)?
+			} else {
+				// Count the lines before finding anything, so that a partial group at the end isn't silently dropped
+				group_items(
+					&lines.collect::<Vec<_>>(),
+					args.group_size,
+					args.algo,
+					args.parallel,
+				)?
+			};
+
+			if args.report.verbose {
+				for (i, item) in &items {
+					writeln!(out, "{}: {}", i + 1, char::from(*item))?;
+				}
+			}
+
+			writeln!(
+				out,
+				"{}",
+				items
+					.iter()
+					.map(|(_, item)| priority(*item).map(u64::from))
+					.sum::<Result<u64>>()?
+			)?;
+		}
+		Mode::Both => {
+			// The two variants go through the lines differently, so keep them around to go through twice
+			let lines = lines.collect::<Vec<_>>();
+
+			writeln!(
+				out,
+				"Single: {}",
+				single_priorities(lines.iter(), args.algo)?
+			)?;
+			writeln!(
+				out,
+				"Triple: {}",
+				group_priorities(&lines, args.group_size, args.algo)?
+			)?;
+		}
+		Mode::Common => match common_items(lines).as_slice() {
+			[] => writeln!(out, "No item is common to every rucksack")?,
+			items => writeln!(out, "{}", String::from_utf8_lossy(items))?,
+		},
+		Mode::Duplicates => writeln!(out, "{}", duplicate_priorities(lines)?)?,
+		Mode::Rarest => {
+			let (count, items) = rarest_items(lines)?;
+
+			if items.is_empty() {
+				writeln!(out, "No items in any rucksack")?;
+			}
+			for item in items {
+				writeln!(
+					out,
+					"{} (priority {}) appears {count} times",
+					char::from(item),
+					priority(item)?
+				)?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn main() -> Result<()> {
 	let args = Args::parse();
 
-	let file = File::open(args.input_file)?;
+	if args.report.which && !matches!(args.mode, Mode::Single | Mode::Triple) {
+		return Err(anyhow!("--which only works in single or triple mode"));
+	}
+
+	let file = File::open(&args.input_file)?;
 
 	// Get an iterator over the lines of the input file
 	let lines = io::BufReader::new(file)
@@ -116,21 +628,23 @@ fn main() -> Result<(), Box<dyn Error>> {
 		.flatten()
 		.map(std::string::String::into_bytes);
 
-	// Convert the lines into common items (either in halves of a sack or between multiple sacks) depending on mode
-	let item_iter: Box<dyn Iterator<Item = _>> = match args.mode {
-		Mode::Single => Box::new(lines.map(|sack| get_common_item(split_sacks::<2>(&sack)))),
-		Mode::Triple => Box::new(
-			lines
-				.array_chunks::<3>()
-				// Annoying type conversions
-				.map(|sacks| get_common_item(sacks.each_ref().map(|v| &v[..]))),
-		),
+	// A line which isn't ascii is reported once the lines have been gone through
+	let mut not_ascii = None;
+	let lines: Box<dyn Iterator<Item = Vec<u8>> + Send> = if args.no_ascii_check {
+		Box::new(lines)
+	} else {
+		Box::new(ascii_lines(lines, &mut not_ascii))
 	};
 
-	// Convert common items into priorities, then sum
-	let sum = item_iter.map(|item| u64::from(priority(item))).sum::<u64>();
+	// Found items are only printed once every line has been checked, so collect the output until then
+	let mut out = Vec::new();
+	let result = run(&mut out, &args, lines);
+	if let Some(err) = not_ascii {
+		return Err(err);
+	}
+	result?;
 
-	println!("{sum}");
+	io::stdout().write_all(&out)?;
 
 	Ok(())
 }
@@ -144,9 +658,9 @@ mod tests {
 	fn test_common_items() {
 		macro_rules! test_first {
 			($exp1:expr, $exp2:expr) => {
-				let sacks = split_sacks::<2>($exp1);
+				let sacks = split_sacks::<2>($exp1).unwrap();
 				assert_eq!(
-					get_common_item(sacks) as char,
+					get_common_item(sacks).unwrap() as char,
 					$exp2,
 					"Finding similar item in\n  left: `{}`\n right: `{}`",
 					String::from_utf8_lossy(sacks[0]),
@@ -166,7 +680,8 @@ mod tests {
 				b"vJrwpWtwJgWrhcsFMMfFFhFp",
 				b"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
 				b"PmmdzqPrVvPwwTWBwg"
-			]) as char,
+			])
+			.unwrap() as char,
 			'r'
 		);
 		assert_eq!(
@@ -174,8 +689,288 @@ mod tests {
 				b"wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
 				b"ttgJtRGJQctTZtZT",
 				b"CrZsJsPPZsGzwwsLwLmpwMDw"
-			]) as char,
+			])
+			.unwrap() as char,
 			'Z'
 		);
 	}
+
+	#[test]
+	fn both() {
+		let lines = [
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+			"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+			"PmmdzqPrVvPwwTWBwg",
+			"wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+			"ttgJtRGJQctTZtZT",
+			"CrZsJsPPZsGzwwsLwLmpwMDw",
+		];
+
+		assert_eq!(single_priorities(lines.iter(), Algo::Merge).unwrap(), 157);
+		assert_eq!(triple_priorities(lines.iter(), Algo::Merge).unwrap(), 70);
+	}
+
+	#[test]
+	fn common() {
+		assert_eq!(common_items(["abcX", "Xdef", "ghXi"].iter()), b"X");
+		assert_eq!(common_items(["abc", "def"].iter()), b"");
+		assert_eq!(common_items(std::iter::empty::<&str>()), b"");
+	}
+
+	#[test]
+	fn duplicates() {
+		// `a` and `Z` are both duplicated, and `Z` three times is still only counted once
+		assert_eq!(duplicate_priority(b"abZcaZdZ").unwrap(), 1 + 52);
+		assert_eq!(duplicate_priority(b"abc").unwrap(), 0);
+		assert_eq!(
+			duplicate_priorities(["abZcaZdZ", "abc", "xx"].iter()).unwrap(),
+			53 + 24
+		);
+	}
+
+	#[test]
+	fn rarest() {
+		// `c` and `Q` both only appear once, across different sacks
+		assert_eq!(
+			rarest_items(["aabQ", "bbac", "ab"].iter()).unwrap(),
+			(1, b"cQ".to_vec())
+		);
+		assert_eq!(
+			rarest_items(["aZ", "Za"].iter()).unwrap(),
+			(2, b"aZ".to_vec())
+		);
+		assert_eq!(rarest_items(["", ""].iter()).unwrap(), (0, Vec::new()));
+		assert!(rarest_items(["ab", "a1"].iter()).is_err());
+	}
+
+	#[test]
+	fn algorithms() {
+		let lines = [
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+			"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+			"PmmdzqPrVvPwwTWBwg",
+			"wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+			"ttgJtRGJQctTZtZT",
+			"CrZsJsPPZsGzwwsLwLmpwMDw",
+		];
+
+		for line in lines {
+			let sacks = split_sacks::<2>(line.as_bytes()).unwrap();
+			assert_eq!(
+				get_common_item_set(sacks).unwrap(),
+				get_common_item(sacks).unwrap()
+			);
+			assert_eq!(
+				get_common_item_bits(sacks).unwrap(),
+				get_common_item(sacks).unwrap()
+			);
+		}
+
+		for algo in [Algo::Merge, Algo::Set, Algo::Bits] {
+			assert_eq!(single_priorities(lines.iter(), algo).unwrap(), 157);
+			assert_eq!(triple_priorities(lines.iter(), algo).unwrap(), 70);
+
+			// When more than one item is common, every algorithm finds the one with the smallest ascii value
+			assert_eq!(
+				algo.common_item(split_sacks::<2>(b"zaBbAZzbAa").unwrap())
+					.unwrap(),
+				b'A'
+			);
+			assert_eq!(
+				algo.common_item([b"cab".as_slice(), b"bca", b"xbac"])
+					.unwrap(),
+				b'a'
+			);
+			assert_eq!(
+				algo.common_item([b"ZqYz".as_slice(), b"zYZ", b"YZz"])
+					.unwrap(),
+				b'Y'
+			);
+		}
+	}
+
+	#[test]
+	fn parallel() {
+		let lines = [
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+			"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+			"PmmdzqPrVvPwwTWBwg",
+			"wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+			"ttgJtRGJQctTZtZT",
+			"CrZsJsPPZsGzwwsLwLmpwMDw",
+		];
+
+		let items = single_items(lines.iter(), Algo::Merge, true).unwrap();
+		assert_eq!(
+			items,
+			single_items(lines.iter(), Algo::Merge, false).unwrap()
+		);
+		assert_eq!(
+			items,
+			[
+				(0, b'p'),
+				(1, b'L'),
+				(2, b'P'),
+				(3, b'v'),
+				(4, b't'),
+				(5, b's')
+			]
+		);
+
+		let items = triple_items(lines.iter(), Algo::Merge, true).unwrap();
+		assert_eq!(
+			items,
+			triple_items(lines.iter(), Algo::Merge, false).unwrap()
+		);
+		assert_eq!(items, [(0, b'r'), (1, b'Z')]);
+	}
+
+	#[test]
+	fn malformed() {
+		let lines = [
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+			"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+			"PmmdzqPrVvPwwTWBwg",
+			// Nothing in common with the rest of the group
+			"abc",
+			"ttgJtRGJQctTZtZT",
+			"CrZsJsPPZsGzwwsLwLmpwMDw",
+		];
+
+		for algo in [Algo::Merge, Algo::Set, Algo::Bits] {
+			assert!(triple_priorities(lines.iter(), algo).is_err());
+			// An odd number of items can't be split in half
+			assert!(single_priorities(lines.iter(), algo).is_err());
+		}
+		assert!(triple_items(lines.iter(), Algo::Merge, true).is_err());
+
+		// Items have to be letters
+		assert!(single_priorities(["ab1b"].iter(), Algo::Bits).is_err());
+		assert!(duplicate_priorities(["a b "].iter()).is_err());
+		assert!(single_priorities([""].iter(), Algo::Merge).is_err());
+	}
+
+	#[test]
+	fn group_size() {
+		let lines = [
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+			"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+			"PmmdzqPrVvPwwTWBwg",
+			"wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+			"ttgJtRGJQctTZtZT",
+			"CrZsJsPPZsGzwwsLwLmpwMDw",
+			"abc",
+		];
+
+		// The 7th line would be silently dropped by `array_chunks`
+		let err = group_priorities(&lines, 3, Algo::Merge).unwrap_err();
+		assert!(
+			err.to_string().contains("leaving 1 leftover lines"),
+			"{err}"
+		);
+		assert!(group_items(&lines, 3, Algo::Merge, true).is_err());
+		assert!(group_items(&lines[..6], 0, Algo::Merge, false).is_err());
+
+		assert_eq!(group_priorities(&lines[..6], 3, Algo::Bits).unwrap(), 70);
+		// F, B and G
+		assert_eq!(
+			group_priorities(&lines[..6], 2, Algo::Merge).unwrap(),
+			32 + 28 + 33
+		);
+		assert_eq!(
+			group_items(&lines[..6], 2, Algo::Merge, true).unwrap(),
+			[(0, b'F'), (1, b'B'), (2, b'G')]
+		);
+		assert!(group_priorities(&lines[..6], 6, Algo::Merge).is_err());
+	}
+
+	#[test]
+	fn which() {
+		// Once sorted, the halves are `JJWWgprrtvww` and `FFFFMMcfhhps`. The first half reaches `p` first,
+		// then the second half is walked onto it
+		assert_eq!(
+			get_common_item_found(split_sacks::<2>(b"vJrwpWtwJgWrhcsFMMfFFhFp").unwrap()).unwrap(),
+			Found {
+				item: b'p',
+				sack: 1,
+				position: 10
+			}
+		);
+		// Sorting both puts `a` first in each, so nothing is walked
+		assert_eq!(
+			get_common_item_found([b"xa", b"ay"]).unwrap(),
+			Found {
+				item: b'a',
+				sack: 0,
+				position: 0
+			}
+		);
+		// The second sack has to be walked past `a` and `b` to reach `c`
+		assert_eq!(
+			get_common_item_found([b"cd", b"bca"]).unwrap(),
+			Found {
+				item: b'c',
+				sack: 1,
+				position: 2
+			}
+		);
+
+		let lines = [
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+			"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+			"PmmdzqPrVvPwwTWBwg",
+		];
+		let found = which_items(lines.iter(), &Mode::Triple, 3, false).unwrap();
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].1.item, b'r');
+		assert!(which_items(lines.iter(), &Mode::Triple, 2, false).is_err());
+	}
+
+	#[test]
+	fn dedupe_lines() {
+		let lines = [
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+			"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+		];
+
+		// The repeated `p` is only counted once
+		assert_eq!(
+			single_priorities(lines.iter(), Algo::Merge).unwrap(),
+			16 + 38 + 16
+		);
+		assert_eq!(
+			single_priorities(dedupe(lines.iter()), Algo::Merge).unwrap(),
+			16 + 38
+		);
+		assert_eq!(dedupe(lines.iter()).count(), 2);
+	}
+
+	#[test]
+	fn ascii() {
+		let lines = [
+			"vJrwpWtwJgWrhcsFMMfFFhFp",
+			"jqHRNqRjqzjGDéLGLrsFMfFZSrLrFZsSL",
+		];
+
+		let err = check_ascii(1, lines[1].as_bytes()).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"Line 2 is not ascii: `jqHRNqRjqzjGDéLGLrsFMfFZSrLrFZsSL`"
+		);
+		assert!(check_ascii(0, lines[0].as_bytes()).is_ok());
+
+		// Lines are checked as they're read, so the lines before a bad one are still gone through
+		let mut not_ascii = None;
+		let checked = ascii_lines(
+			lines.iter().map(|line| line.as_bytes().to_vec()),
+			&mut not_ascii,
+		)
+		.collect::<Vec<_>>();
+		assert_eq!(checked, [lines[0].as_bytes()]);
+		assert_eq!(
+			not_ascii.unwrap().to_string(),
+			"Line 2 is not ascii: `jqHRNqRjqzjGDéLGLrsFMfFZSrLrFZsSL`"
+		);
+	}
 }
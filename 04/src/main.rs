@@ -1,15 +1,19 @@
 #![deny(clippy::pedantic)]
 use std::{
+	collections::BTreeMap,
 	fs::File,
-	io::{self, BufRead},
+	io::{self, BufRead, IsTerminal},
 	path::PathBuf,
 	str::FromStr,
+	time::Duration,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::{Parser, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, ValueEnum)]
 enum Mode {
@@ -17,6 +21,50 @@ enum Mode {
 	Entire,
 	/// The second variant of the problem, where we check if in a pair of assignments, one overlaps the other at all
 	Partial,
+	/// Both variants of the problem at once, reading the input file only once
+	Both,
+}
+
+#[derive(Clone, ValueEnum)]
+enum Format {
+	/// One count per line
+	Plain,
+	/// A single object with the counts of each variant and the total number of pairs, for use with other programs
+	Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+/// The order to list pairs of assignments in
+enum Sort {
+	/// The same order as the input file
+	None,
+	/// By the number of sections in both assignments, smallest first
+	Size,
+	/// By the first section of the first assignment, lowest first
+	Start,
+}
+
+#[derive(clap::Args)]
+struct ListArgs {
+	/// Also list every pair of assignments which was counted, along with how many sections are in both assignments
+	#[arg(long, conflicts_with_all = ["validate", "query", "merge"])]
+	list: bool,
+	/// The order to list pairs of assignments in
+	#[arg(long, value_enum, default_value_t = Sort::None, requires = "list")]
+	sort: Sort,
+	/// After counting, also print how many pairs of assignments have each number of sections in both assignments
+	#[arg(long, conflicts_with_all = ["validate", "query", "merge"])]
+	summary: bool,
+}
+
+#[derive(clap::Args)]
+struct SectionArgs {
+	/// Treat the second number of each assignment as exclusive, so that assignments are half-open ranges `[start, end)`
+	#[arg(long)]
+	exclusive_end: bool,
+	/// Allow sections to be numbered with negative numbers, such as `-3--1,-2-0`
+	#[arg(long)]
+	signed: bool,
 }
 
 #[derive(Parser)]
@@ -25,75 +73,660 @@ struct Args {
 	#[arg(short, long, default_value = "input.txt")]
 	input_file: PathBuf,
 	/// What mode to run the program in
-	#[arg(value_enum)]
-	mode: Mode,
+	#[arg(value_enum, required_unless_present_any = ["validate", "query", "merge"])]
+	mode: Option<Mode>,
+	#[command(flatten)]
+	sections: SectionArgs,
+	/// Count the pairs which don't satisfy the mode (or the query) instead of the ones which do
+	#[arg(long)]
+	invert: bool,
+	/// How to print the counts in both mode
+	#[arg(long, value_enum, default_value_t = Format::Plain)]
+	format: Format,
+	/// Instead of counting overlaps, check every line of the input file and report all of the malformed ones
+	#[arg(long)]
+	validate: bool,
+	/// Instead of counting overlaps, count the pairs of assignments where either assignment overlaps the range of sections `L-R`
+	#[arg(long, value_name = "L-R", value_parser = parse_range)]
+	query: Option<(i64, i64)>,
+	/// Instead of counting overlaps, merge every assignment in the file into as few ranges of sections as possible,
+	/// and list those ranges in order
+	#[arg(long, conflicts_with_all = ["validate", "query", "invert"])]
+	merge: bool,
+	#[command(flatten)]
+	progress: ProgressArgs,
+	#[command(flatten)]
+	list: ListArgs,
+}
+
+#[derive(clap::Args)]
+struct ProgressArgs {
+	/// Show a spinner with the number of lines processed so far for very large input files.
+	/// Only shown when running in a terminal
+	#[arg(long)]
+	progress: bool,
+	/// How often to redraw the progress spinner, in milliseconds
+	#[arg(long, value_name = "MS", default_value_t = 100, requires = "progress")]
+	progress_ms: u64,
+}
+
+/// A spinner counting lines as they're processed, redrawn every `progress_ms` milliseconds.
+/// Hidden unless enabled, and someone is watching.
+fn progress_spinner(args: &ProgressArgs) -> ProgressBar {
+	if !args.progress || !io::stderr().is_terminal() {
+		return ProgressBar::hidden();
+	}
+
+	let pb = ProgressBar::new_spinner().with_style(
+		ProgressStyle::with_template(
+			"{spinner:.green} [{elapsed_precise}] {human_pos} lines ({per_sec})",
+		)
+		.unwrap(),
+	);
+	// Don't redraw every time a line is processed. Instead do it on a steady tick.
+	pb.enable_steady_tick(Duration::from_millis(args.progress_ms));
+
+	pb
+}
+
+/// Parse a range of sections `L-R`, as given on the command line. Either section may be negative, such as `-3--1`.
+fn parse_range(text: &str) -> Result<(i64, i64)> {
+	// The `-` between the sections is the first one which isn't the sign of the start section
+	let split = text
+		.get(1..)
+		.and_then(|rest| rest.find('-'))
+		.ok_or_else(|| anyhow!("`{text}` is not a range of sections"))?
+		+ 1;
+
+	Ok((text[..split].parse()?, text[split + 1..].parse()?))
+}
+
+/// A range of sections as a half-open range `[start, end)`. If the range includes its end section
+/// (`EXCLUSIVE_END` is false), then the end of the range is moved up by one.
+/// Widened so that moving the end up can't overflow, even for the largest section.
+fn half_open<const EXCLUSIVE_END: bool>((start, end): (i64, i64)) -> (i128, i128) {
+	(
+		i128::from(start),
+		i128::from(end) + i128::from(!EXCLUSIVE_END),
+	)
 }
 
 /// A pair of section assignments. Each section assignment is a pair of numbers, which represent a range of sections.
-struct Assignments((u32, u32), (u32, u32));
+struct Assignments((i64, i64), (i64, i64));
 
 impl Assignments {
+	/// Parse a pair of assignments such as `2-4,6-8`. Sections are unsigned 32-bit numbers unless `SIGNED`,
+	/// in which case they may also be negative, such as `-3--1,-2-0`.
+	fn parse<const SIGNED: bool>(text: &str) -> Result<Self> {
+		// Lazily initialize static regular expressions for parsing a pair of assignments
+		lazy_static! {
+			static ref REGEX: Regex =
+				Regex::new("^([[:digit:]]+)-([[:digit:]]+),([[:digit:]]+)-([[:digit:]]+)$")
+					.unwrap();
+			static ref SIGNED_REGEX: Regex =
+				Regex::new("^(-?[[:digit:]]+)-(-?[[:digit:]]+),(-?[[:digit:]]+)-(-?[[:digit:]]+)$")
+					.unwrap();
+		}
+
+		let regex: &Regex = if SIGNED { &SIGNED_REGEX } else { &REGEX };
+
+		// Each number above is captured in a capture group - use those to parse
+		let captures = regex
+			.captures(text)
+			.ok_or_else(|| anyhow!("`{text}` is not a pair of assignments"))?;
+		let section = |i: usize| -> Result<i64> {
+			if SIGNED {
+				Ok(captures[i].parse()?)
+			} else {
+				Ok(i64::from(captures[i].parse::<u32>()?))
+			}
+		};
+
+		Ok(Assignments(
+			(section(1)?, section(2)?),
+			(section(3)?, section(4)?),
+		))
+	}
+
+	/// The assignments as half-open ranges of sections `[start, end)`, as in [`half_open`]
+	fn half_open<const EXCLUSIVE_END: bool>(&self) -> ((i128, i128), (i128, i128)) {
+		(
+			half_open::<EXCLUSIVE_END>(self.0),
+			half_open::<EXCLUSIVE_END>(self.1),
+		)
+	}
+
+	/// The half-open range of sections which are in both assignments, or `None` if there aren't any
+	fn intersection<const EXCLUSIVE_END: bool>(&self) -> Option<(i128, i128)> {
+		let ((start0, end0), (start1, end1)) = self.half_open::<EXCLUSIVE_END>();
+
+		// The overlap starts at the later start, and ends at the earlier end
+		let (start, end) = (start0.max(start1), end0.min(end1));
+
+		(start < end).then_some((start, end))
+	}
+
+	/// The number of sections which are in both assignments
+	fn overlap_size<const EXCLUSIVE_END: bool>(&self) -> u128 {
+		self.intersection::<EXCLUSIVE_END>()
+			.map_or(0, |(start, end)| end.abs_diff(start))
+	}
+
+	/// Which assignment fully contains the other - 0 for the first, or 1 for the second - or `None` if neither does.
+	/// If the assignments are the same, the first one is the container.
+	fn container<const EXCLUSIVE_END: bool>(&self) -> Option<usize> {
+		let (first, second) = self.half_open::<EXCLUSIVE_END>();
+		// An empty assignment doesn't overlap anything, so it doesn't contain anything and isn't contained by anything.
+		let overlap = self.intersection::<EXCLUSIVE_END>()?;
+
+		// One assignment contains the other if the overlap is the whole of the other assignment
+		if overlap == second {
+			Some(0)
+		} else if overlap == first {
+			Some(1)
+		} else {
+			None
+		}
+	}
+
 	/// Test if one assignment fully contains the other
-	fn overlaps_entirely(&self) -> bool {
-		(self.0 .0 >= self.1 .0 && self.0 .1 <= self.1 .1)
-			|| (self.0 .0 <= self.1 .0 && self.0 .1 >= self.1 .1)
+	fn overlaps_entirely<const EXCLUSIVE_END: bool>(&self) -> bool {
+		self.container::<EXCLUSIVE_END>().is_some()
 	}
 
 	/// Test if the assignments overlap at all
-	fn overlaps_partially(&self) -> bool {
-		!((self.0 .0 < self.1 .0 && self.0 .1 < self.1 .0)
-			|| (self.0 .0 > self.1 .1 && self.0 .1 > self.1 .1))
+	fn overlaps_partially<const EXCLUSIVE_END: bool>(&self) -> bool {
+		self.intersection::<EXCLUSIVE_END>().is_some()
 	}
 }
 
 impl FromStr for Assignments {
 	type Err = anyhow::Error;
 
+	/// Parse a pair of assignments with unsigned sections, as in [`Assignments::parse`]
 	fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
-		// Lazily initialize a static regular expression for parsing a pair of assignments
-		lazy_static! {
-			static ref REGEX: Regex =
-				Regex::new("^([[:digit:]]+)-([[:digit:]]+),([[:digit:]]+)-([[:digit:]]+)$")
-					.unwrap();
+		Self::parse::<false>(text)
+	}
+}
+
+/// An index over many pairs of assignments, for counting how many pairs overlap a range of sections without
+/// checking every pair. Built from sorted endpoints, so most of each query is a few binary searches. The only pairs which are
+/// still checked one by one are those with a gap between their assignments which starts early enough to fit the range,
+/// so a query can take as long as checking every pair when most pairs have gaps.
+struct PairIndex {
+	/// The number of pairs in the index
+	len: usize,
+	/// The number of pairs where both assignments are empty, which never overlap anything
+	empty: usize,
+	/// For every pair, the end of the assignment which ends last, sorted
+	max_ends: Vec<i128>,
+	/// For every pair, the start of the assignment which starts first, sorted
+	min_starts: Vec<i128>,
+	/// For every pair of assignments which don't overlap each other, the gap of sections between them `[end, start)`,
+	/// sorted by where they start
+	gaps: Vec<(i128, i128)>,
+}
+
+impl PairIndex {
+	/// Build an index over pairs of assignments, treating the end of each assignment as in [`half_open`]
+	fn new<const EXCLUSIVE_END: bool, T: Iterator<Item = Assignments>>(pairs: T) -> Self {
+		let mut index = PairIndex {
+			len: 0,
+			empty: 0,
+			max_ends: Vec::new(),
+			min_starts: Vec::new(),
+			gaps: Vec::new(),
+		};
+
+		for pair in pairs {
+			index.len += 1;
+
+			let (first, second) = pair.half_open::<EXCLUSIVE_END>();
+			// Empty assignments can't overlap anything, so ignore them
+			let mut ranges = [first, second]
+				.into_iter()
+				.filter(|(start, end)| start < end)
+				.collect::<Vec<_>>();
+			// Put the assignment which ends first at the front
+			ranges.sort_unstable_by_key(|(_, end)| *end);
+
+			match ranges[..] {
+				[] => index.empty += 1,
+				[(start, end)] => {
+					index.max_ends.push(end);
+					index.min_starts.push(start);
+				}
+				[(start0, end0), (start1, end1)] => {
+					index.max_ends.push(end1);
+					index.min_starts.push(start0.min(start1));
+
+					// There's only a gap between the assignments if the second starts after the first ends
+					if end0 < start1 {
+						index.gaps.push((end0, start1));
+					}
+				}
+				_ => unreachable!(),
+			}
 		}
 
-		// Each number above is captured in a capture group - use those to parse
-		let captures = REGEX.captures(text).unwrap();
+		index.max_ends.sort_unstable();
+		index.min_starts.sort_unstable();
+		index.gaps.sort_unstable();
 
-		Ok(Assignments(
-			(captures[1].parse()?, captures[2].parse()?),
-			(captures[3].parse()?, captures[4].parse()?),
-		))
+		index
+	}
+
+	/// Count the pairs where either assignment overlaps the half-open range of sections `[start, end)`
+	fn count_overlapping(&self, (start, end): (i128, i128)) -> usize {
+		// An empty range doesn't overlap anything
+		if start >= end {
+			return 0;
+		}
+
+		// A pair doesn't overlap the range if both of its assignments are before the range...
+		let before = self.max_ends.partition_point(|max_end| *max_end <= start);
+		// ...or both of its assignments are after the range...
+		let after = self.min_starts.len()
+			- self
+				.min_starts
+				.partition_point(|min_start| *min_start < end);
+		// ...or the range fits entirely in the gap between its assignments. Only gaps which start early enough can fit the range.
+		let around = self.gaps[..self
+			.gaps
+			.partition_point(|(gap_start, _)| *gap_start <= start)]
+			.iter()
+			.filter(|(_, gap_end)| *gap_end >= end)
+			.count();
+
+		// Every other pair overlaps the range
+		self.len - self.empty - before - after - around
 	}
 }
 
-fn main() -> Result<()> {
-	let args = Args::parse();
+/// Merge half-open ranges of sections `[start, end)` into the fewest ranges which cover the same sections, sorted by where they start.
+/// Empty ranges don't cover any sections, so they're dropped. Ranges which touch are merged, since there's no gap between them.
+fn merge_ranges<T: Iterator<Item = (i128, i128)>>(ranges: T) -> Vec<(i128, i128)> {
+	let mut ranges = ranges
+		.filter(|(start, end)| start < end)
+		.collect::<Vec<_>>();
+	ranges.sort_unstable();
 
-	let file = File::open(args.input_file)?;
+	let mut merged: Vec<(i128, i128)> = Vec::new();
+	for (start, end) in ranges {
+		match merged.last_mut() {
+			// Ranges are sorted by start, so this range can only overlap (or touch) the last merged range
+			Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+			_ => merged.push((start, end)),
+		}
+	}
 
-	// Change modes based on which part of the problem
-	let overlaps = match args.mode {
-		Mode::Entire => Assignments::overlaps_entirely,
-		Mode::Partial => Assignments::overlaps_partially,
-	};
+	merged
+}
+
+/// Merge every assignment (parsed with `parse`) into the fewest half-open ranges of sections, as in [`merge_ranges`]
+fn merge_assignments<T: Iterator<Item = String>>(
+	lines: T,
+	parse: fn(&str) -> Result<Assignments>,
+	exclusive_end: bool,
+) -> Vec<(i128, i128)> {
+	merge_ranges(lines.flat_map(|s| parse(&s)).flat_map(|pair| {
+		let (first, second) = if exclusive_end {
+			pair.half_open::<true>()
+		} else {
+			pair.half_open::<false>()
+		};
+		[first, second]
+	}))
+}
+
+/// Count the pairs where either assignment overlaps the range of sections `query` with a [`PairIndex`],
+/// along with the number of pairs in the index
+fn query_overlaps<T: Iterator<Item = Assignments>>(
+	pairs: T,
+	query: (i64, i64),
+	exclusive_end: bool,
+) -> (usize, usize) {
+	if exclusive_end {
+		let index = PairIndex::new::<true, _>(pairs);
+		(index.count_overlapping(half_open::<true>(query)), index.len)
+	} else {
+		let index = PairIndex::new::<false, _>(pairs);
+		(
+			index.count_overlapping(half_open::<false>(query)),
+			index.len,
+		)
+	}
+}
 
-	let overlaps: u32 = io::BufReader::new(file)
-		.lines()
-		// Skip lines which couldn't be read
-		.flatten()
+/// Try to parse every line as a pair of assignments with `parse`, and collect every line which failed to parse
+/// along with its (1-based) line number and the reason it failed.
+fn malformed_lines<T: Iterator<Item = String>>(
+	lines: T,
+	parse: fn(&str) -> Result<Assignments>,
+) -> Vec<(usize, anyhow::Error)> {
+	lines
+		.enumerate()
+		.filter_map(|(i, line)| parse(&line).err().map(|err| (i + 1, err)))
+		.collect()
+}
+
+/// Count the pairs of assignments (parsed with `parse`) which overlap according to `overlaps`, or which don't if `invert`
+fn count_overlaps<T: Iterator<Item = String>>(
+	lines: T,
+	parse: fn(&str) -> Result<Assignments>,
+	overlaps: fn(&Assignments) -> bool,
+	invert: bool,
+) -> u32 {
+	lines
 		// Parse lines as assignment pairs
-		.flat_map(|s| s.parse::<Assignments>())
-		// Check if assignment pair overlaps - if so, count it (as 1)
-		.map(|assignment| u32::from(overlaps(&assignment)))
+		.flat_map(|s| parse(&s))
+		// Check if assignment pair overlaps (or doesn't, if inverted) - if so, count it (as 1)
+		.map(|assignment| u32::from(overlaps(&assignment) != invert))
 		// Then sum overlapping assignments
-		.sum();
+		.sum()
+}
 
-	println!("No. overlapping assignments: {overlaps}");
+/// The counts of both variants of the problem, from a single pass through the input file
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct BothCounts {
+	/// The number of pairs where one assignment overlaps the other entirely (or doesn't, if inverted)
+	entire: u32,
+	/// The number of pairs where one assignment overlaps the other at all (or doesn't, if inverted)
+	partial: u32,
+	/// The number of pairs in the input file
+	total: u32,
+}
+
+/// Count the pairs of assignments (parsed with `parse`) which overlap entirely and partially at the same time,
+/// or which don't if `invert`, as in [`count_overlaps`]
+fn count_both<const EXCLUSIVE_END: bool, T: Iterator<Item = String>>(
+	lines: T,
+	parse: fn(&str) -> Result<Assignments>,
+	invert: bool,
+) -> BothCounts {
+	lines
+		// Parse lines as assignment pairs
+		.flat_map(|s| parse(&s))
+		// Then count each pair for each variant it satisfies, and for the total
+		.fold(
+			BothCounts {
+				entire: 0,
+				partial: 0,
+				total: 0,
+			},
+			|counts, assignment| BothCounts {
+				entire: counts.entire
+					+ u32::from(assignment.overlaps_entirely::<EXCLUSIVE_END>() != invert),
+				partial: counts.partial
+					+ u32::from(assignment.overlaps_partially::<EXCLUSIVE_END>() != invert),
+				total: counts.total + 1,
+			},
+		)
+}
+
+/// A pair of assignments to be listed
+#[derive(Debug, PartialEq, Eq)]
+struct ListedPair {
+	/// The (1-based) line of the input file the pair is on
+	line_num: usize,
+	/// The pair, as written in the input file
+	text: String,
+	/// The first section of the first assignment
+	start: i64,
+	/// The number of sections in both assignments
+	size: u128,
+	/// Which assignment contains the other, as in [`Assignments::container`]
+	container: Option<usize>,
+}
+
+/// The pairs of assignments which overlap according to `overlaps` (or which don't, if `invert`) in the order given by `sort`,
+/// which are the pairs that [`count_overlaps`] counts
+fn list_overlaps<T: Iterator<Item = String>>(
+	lines: T,
+	parse: fn(&str) -> Result<Assignments>,
+	overlaps: fn(&Assignments) -> bool,
+	overlap_size: fn(&Assignments) -> u128,
+	container: fn(&Assignments) -> Option<usize>,
+	invert: bool,
+	sort: Sort,
+) -> Vec<ListedPair> {
+	let mut pairs = lines
+		.enumerate()
+		.filter_map(|(i, line)| {
+			let assignment = parse(&line).ok()?;
+
+			(overlaps(&assignment) != invert).then(|| ListedPair {
+				line_num: i + 1,
+				start: assignment.0 .0,
+				size: overlap_size(&assignment),
+				container: container(&assignment),
+				text: line,
+			})
+		})
+		.collect::<Vec<_>>();
+
+	// Stable sorts, so that pairs which are tied stay in the same order as the input file
+	match sort {
+		Sort::None => {}
+		Sort::Size => pairs.sort_by_key(|pair| pair.size),
+		Sort::Start => pairs.sort_by_key(|pair| pair.start),
+	}
+
+	pairs
+}
+
+/// How many pairs of assignments (parsed with `parse`) have each number of sections in both assignments, according to `overlap_size`.
+/// Every pair is counted, whether or not it overlaps, and sizes which no pairs have are left out.
+fn overlap_histogram<T: Iterator<Item = String>>(
+	lines: T,
+	parse: fn(&str) -> Result<Assignments>,
+	overlap_size: fn(&Assignments) -> u128,
+) -> BTreeMap<u128, u32> {
+	lines
+		// Parse lines as assignment pairs
+		.flat_map(|s| parse(&s))
+		// Then tally up the size of each overlap
+		.fold(BTreeMap::new(), |mut histogram, assignment| {
+			*histogram.entry(overlap_size(&assignment)).or_insert(0) += 1;
+			histogram
+		})
+}
+
+/// How to check whether a pair of assignments overlaps for a mode, or `None` for both mode which checks both ways
+fn overlap_check(mode: &Mode, exclusive_end: bool) -> Option<fn(&Assignments) -> bool> {
+	match (mode, exclusive_end) {
+		(Mode::Entire, false) => Some(Assignments::overlaps_entirely::<false>),
+		(Mode::Entire, true) => Some(Assignments::overlaps_entirely::<true>),
+		(Mode::Partial, false) => Some(Assignments::overlaps_partially::<false>),
+		(Mode::Partial, true) => Some(Assignments::overlaps_partially::<true>),
+		(Mode::Both, _) => None,
+	}
+}
+
+/// Print the counts from both mode in the given format
+fn print_both(counts: &BothCounts, format: &Format) -> Result<()> {
+	match format {
+		Format::Plain => {
+			println!("Entire: {}", counts.entire);
+			println!("Partial: {}", counts.partial);
+			println!("Total: {}", counts.total);
+		}
+		Format::Json => println!("{}", serde_json::to_string(counts)?),
+	}
 
 	Ok(())
 }
 
+/// Count (or list) the pairs of assignments which overlap according to `mode`, and print the results
+fn count_mode<T: Iterator<Item = String>>(
+	args: &Args,
+	mode: &Mode,
+	lines: T,
+	parse: fn(&str) -> Result<Assignments>,
+	overlap_size: fn(&Assignments) -> u128,
+	pb: &ProgressBar,
+) -> Result<()> {
+	if let Mode::Both = mode {
+		if args.list.list {
+			return Err(anyhow!(
+				"Both mode counts two kinds of overlap, so it can't list pairs"
+			));
+		}
+
+		let counts = if args.sections.exclusive_end {
+			count_both::<true, _>(lines, parse, args.invert)
+		} else {
+			count_both::<false, _>(lines, parse, args.invert)
+		};
+		pb.finish_and_clear();
+
+		return print_both(&counts, &args.format);
+	}
+
+	// Change modes based on which part of the problem. Both has its own counting, and has already returned.
+	let overlaps = overlap_check(mode, args.sections.exclusive_end).unwrap();
+
+	if args.list.list {
+		let container = if args.sections.exclusive_end {
+			Assignments::container::<true>
+		} else {
+			Assignments::container::<false>
+		};
+		let pairs = list_overlaps(
+			lines,
+			parse,
+			overlaps,
+			overlap_size,
+			container,
+			args.invert,
+			args.list.sort,
+		);
+		pb.finish_and_clear();
+
+		for pair in &pairs {
+			// Assignments are numbered from 1, the same as lines
+			let contains = match pair.container {
+				Some(container) => format!(
+					", assignment {} contains assignment {}",
+					container + 1,
+					2 - container
+				),
+				None => String::new(),
+			};
+
+			println!(
+				"Line {}: {} ({} sections in both{contains})",
+				pair.line_num, pair.text, pair.size
+			);
+		}
+		println!("No. listed assignments: {}", pairs.len());
+
+		return Ok(());
+	}
+
+	let overlaps = count_overlaps(lines, parse, overlaps, args.invert);
+	pb.finish_and_clear();
+
+	let label = if args.invert {
+		"non-overlapping"
+	} else {
+		"overlapping"
+	};
+	println!("No. {label} assignments: {overlaps}");
+
+	Ok(())
+}
+
+fn main() -> Result<()> {
+	let args = Args::parse();
+
+	let file = File::open(&args.input_file)?;
+	let pb = progress_spinner(&args.progress);
+	let lines = pb.wrap_iter(
+		io::BufReader::new(file)
+			.lines()
+			// Skip lines which couldn't be read
+			.flatten(),
+	);
+	let parse = if args.sections.signed {
+		Assignments::parse::<true>
+	} else {
+		Assignments::parse::<false>
+	};
+	let exclusive_end = args.sections.exclusive_end;
+
+	if args.validate {
+		let malformed = malformed_lines(lines, parse);
+		pb.finish_and_clear();
+
+		for (line_num, err) in &malformed {
+			println!("Line {line_num}: {err}");
+		}
+
+		// Exit non-zero if there was anything to report
+		return if malformed.is_empty() {
+			println!("No malformed lines");
+			Ok(())
+		} else {
+			Err(anyhow!("Found {} malformed lines", malformed.len()))
+		};
+	}
+
+	if let Some(query) = args.query {
+		let (count, len) = query_overlaps(lines.flat_map(|s| parse(&s)), query, exclusive_end);
+		pb.finish_and_clear();
+		// Every pair which doesn't overlap the query
+		let count = if args.invert { len - count } else { count };
+
+		let label = if args.invert {
+			"not overlapping"
+		} else {
+			"overlapping"
+		};
+		println!("No. pairs {label} {}-{}: {count}", query.0, query.1);
+
+		return Ok(());
+	}
+
+	if args.merge {
+		let merged = merge_assignments(lines, parse, exclusive_end);
+		pb.finish_and_clear();
+
+		// Print ranges the same way as the input file, so inclusive ranges end one section earlier
+		for (start, end) in &merged {
+			println!("{start}-{}", end - i128::from(!exclusive_end));
+		}
+		println!("No. merged ranges: {}", merged.len());
+
+		return Ok(());
+	}
+
+	// Mode is only optional when validating, querying or merging, so it must be present here.
+	let mode = args.mode.as_ref().unwrap();
+	let overlap_size = if exclusive_end {
+		Assignments::overlap_size::<true>
+	} else {
+		Assignments::overlap_size::<false>
+	};
+
+	if args.list.summary {
+		// The summary goes through the lines as well, so keep them around to go through twice
+		let lines = lines.collect::<Vec<_>>();
+		let histogram = overlap_histogram(lines.iter().cloned(), parse, overlap_size);
+
+		count_mode(&args, mode, lines.into_iter(), parse, overlap_size, &pb)?;
+
+		println!("Sections in both assignments:");
+		for (size, count) in histogram {
+			println!("{size}: {count}");
+		}
+
+		Ok(())
+	} else {
+		count_mode(&args, mode, lines, parse, overlap_size, &pb)
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -103,7 +736,7 @@ mod tests {
 		macro_rules! test_entirely {
 			($str:expr, $truth:expr) => {
 				let assignment: Assignments = $str.parse().unwrap();
-				let overlaps = assignment.overlaps_entirely();
+				let overlaps = assignment.overlaps_entirely::<false>();
 
 				assert_eq!(overlaps, $truth, "(entire overlap)\n  text: `{}`", $str)
 			};
@@ -119,7 +752,7 @@ mod tests {
 		macro_rules! test_partially {
 			($str:expr, $truth:expr) => {
 				let assignment: Assignments = $str.parse().unwrap();
-				let overlaps = assignment.overlaps_partially();
+				let overlaps = assignment.overlaps_partially::<false>();
 
 				assert_eq!(overlaps, $truth, "(partial overlap)\n  text: `{}`", $str)
 			};
@@ -133,6 +766,75 @@ mod tests {
 		test_partially!("2-6,4-8", true);
 	}
 
+	#[test]
+	fn test_exclusive_end() {
+		macro_rules! test {
+			($str:expr, $entirely:expr, $partially:expr) => {
+				let assignment: Assignments = $str.parse().unwrap();
+
+				assert_eq!(
+					assignment.overlaps_entirely::<true>(),
+					$entirely,
+					"(entire overlap)\n  text: `{}`",
+					$str
+				);
+				assert_eq!(
+					assignment.overlaps_partially::<true>(),
+					$partially,
+					"(partial overlap)\n  text: `{}`",
+					$str
+				);
+			};
+		}
+
+		test!("2-4,6-8", false, false);
+		test!("2-3,4-5", false, false);
+		// These ranges touch at 7, which is no longer part of the first range
+		test!("5-7,7-9", false, false);
+		test!("2-8,3-7", true, true);
+		// The first range is now empty, so it doesn't overlap anything
+		test!("6-6,4-6", false, false);
+		test!("2-6,4-8", false, true);
+	}
+
+	#[test]
+	fn test_intersection() {
+		// Check the predicates against the sets of sections in each assignment
+		macro_rules! test {
+			($str:expr, $exclusive_end:literal) => {
+				let assignment: Assignments = $str.parse().unwrap();
+				let ((start0, end0), (start1, end1)) = assignment.half_open::<$exclusive_end>();
+				let sections0 = (start0..end0).collect::<std::collections::HashSet<_>>();
+				let sections1 = (start1..end1).collect::<std::collections::HashSet<_>>();
+
+				let entirely = !sections0.is_empty()
+					&& !sections1.is_empty()
+					&& (sections0.is_subset(&sections1) || sections1.is_subset(&sections0));
+				let partially = !sections0.is_disjoint(&sections1);
+
+				assert_eq!(
+					assignment.overlaps_entirely::<$exclusive_end>(),
+					entirely,
+					"(entire overlap)\n  text: `{}`",
+					$str
+				);
+				assert_eq!(
+					assignment.overlaps_partially::<$exclusive_end>(),
+					partially,
+					"(partial overlap)\n  text: `{}`",
+					$str
+				);
+			};
+		}
+
+		for text in [
+			"2-4,6-8", "2-3,4-5", "5-7,7-9", "2-8,3-7", "6-6,4-6", "2-6,4-8",
+		] {
+			test!(text, false);
+			test!(text, true);
+		}
+	}
+
 	#[test]
 	fn test_parse() {
 		macro_rules! test {
@@ -159,4 +861,304 @@ mod tests {
 		// An extra one to make sure it works with multiple digits (as mentioned in the prompt)
 		test!("22-63,4-888", (22, 63, 4, 888));
 	}
+
+	#[test]
+	fn test_validate() {
+		let text = "2-4,6-8
+2-3,4-5
+5-7;7-9
+2-8,3-7
+6-6,4-6
+2-6,x-8";
+
+		let malformed = malformed_lines(
+			text.lines().map(std::string::ToString::to_string),
+			Assignments::parse::<false>,
+		);
+		let line_nums = malformed.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+
+		assert_eq!(line_nums, [3, 6]);
+	}
+
+	#[test]
+	fn test_signed() {
+		let assignment = Assignments::parse::<true>("-3--1,-2-0").unwrap();
+		assert_eq!((assignment.0, assignment.1), ((-3, -1), (-2, 0)));
+		assert!(assignment.overlaps_partially::<false>());
+		assert!(!assignment.overlaps_entirely::<false>());
+		// -2 and -1 are in both assignments
+		assert_eq!(assignment.overlap_size::<false>(), 2);
+
+		// Unsigned sections are still the default
+		assert!("-3--1,-2-0".parse::<Assignments>().is_err());
+		assert!(Assignments::parse::<true>("2-4,6-8").is_ok());
+		assert!(Assignments::parse::<true>("2--4,6-8").is_ok());
+		assert!(Assignments::parse::<true>("2---4,6-8").is_err());
+
+		let lines = ["-3--1,-2-0", "-8--6,-5--1", "-5-5,-1-1"].map(str::to_string);
+		assert_eq!(
+			count_overlaps(
+				lines.into_iter(),
+				Assignments::parse::<true>,
+				Assignments::overlaps_partially::<false>,
+				false
+			),
+			2
+		);
+
+		assert_eq!(parse_range("-3--1").unwrap(), (-3, -1));
+		assert_eq!(parse_range("3-7").unwrap(), (3, 7));
+		assert!(parse_range("-3").is_err());
+	}
+
+	#[test]
+	fn test_invert() {
+		let text = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+		let lines = || text.lines().map(std::string::ToString::to_string);
+
+		assert_eq!(
+			count_overlaps(
+				lines(),
+				Assignments::parse::<false>,
+				Assignments::overlaps_entirely::<false>,
+				false
+			),
+			2
+		);
+		assert_eq!(
+			count_overlaps(
+				lines(),
+				Assignments::parse::<false>,
+				Assignments::overlaps_entirely::<false>,
+				true
+			),
+			4
+		);
+		assert_eq!(
+			count_overlaps(
+				lines(),
+				Assignments::parse::<false>,
+				Assignments::overlaps_partially::<false>,
+				true
+			),
+			2
+		);
+	}
+
+	#[test]
+	fn test_both() {
+		let text = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+		let lines = || text.lines().map(std::string::ToString::to_string);
+
+		let counts = count_both::<false, _>(lines(), Assignments::parse::<false>, false);
+		let json = serde_json::to_string(&counts).unwrap();
+		let counts: BothCounts = serde_json::from_str(&json).unwrap();
+		assert_eq!(
+			counts,
+			BothCounts {
+				entire: 2,
+				partial: 4,
+				total: 6
+			}
+		);
+
+		let counts = count_both::<false, _>(lines(), Assignments::parse::<false>, true);
+		assert_eq!((counts.entire, counts.partial, counts.total), (4, 2, 6));
+	}
+
+	#[test]
+	fn test_list() {
+		let text = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+		let list = |sort| {
+			list_overlaps(
+				text.lines().map(std::string::ToString::to_string),
+				Assignments::parse::<false>,
+				Assignments::overlaps_partially::<false>,
+				Assignments::overlap_size::<false>,
+				Assignments::container::<false>,
+				false,
+				sort,
+			)
+			.iter()
+			.map(|pair| (pair.line_num, pair.size))
+			.collect::<Vec<_>>()
+		};
+
+		assert_eq!(list(Sort::None), [(3, 1), (4, 5), (5, 1), (6, 3)]);
+		assert_eq!(list(Sort::Size), [(3, 1), (5, 1), (6, 3), (4, 5)]);
+		// Lines 4 and 6 both start at section 2
+		assert_eq!(list(Sort::Start), [(4, 5), (6, 3), (3, 1), (5, 1)]);
+	}
+
+	#[test]
+	fn test_container() {
+		macro_rules! test {
+			($str:expr, $container:expr) => {
+				let assignment: Assignments = $str.parse().unwrap();
+
+				assert_eq!(
+					assignment.container::<false>(),
+					$container,
+					"(container)\n  text: `{}`",
+					$str
+				);
+			};
+		}
+
+		// The example's fully overlapping pairs, one each way around
+		test!("2-8,3-7", Some(0));
+		test!("6-6,4-6", Some(1));
+		test!("2-6,4-8", None);
+		test!("2-4,6-8", None);
+		// Either contains the other, so the first one does
+		test!("3-5,3-5", Some(0));
+
+		let text = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+		let containers = list_overlaps(
+			text.lines().map(std::string::ToString::to_string),
+			Assignments::parse::<false>,
+			Assignments::overlaps_entirely::<false>,
+			Assignments::overlap_size::<false>,
+			Assignments::container::<false>,
+			false,
+			Sort::None,
+		)
+		.iter()
+		.map(|pair| (pair.line_num, pair.container))
+		.collect::<Vec<_>>();
+		assert_eq!(containers, [(4, Some(0)), (5, Some(1))]);
+	}
+
+	#[test]
+	fn test_summary() {
+		let text = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+		let histogram = |overlap_size| {
+			overlap_histogram(
+				text.lines().map(std::string::ToString::to_string),
+				Assignments::parse::<false>,
+				overlap_size,
+			)
+			.into_iter()
+			.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			histogram(Assignments::overlap_size::<false>),
+			[(0, 2), (1, 2), (3, 1), (5, 1)]
+		);
+		// 5-7,7-9 and 6-6,4-6 no longer overlap
+		assert_eq!(
+			histogram(Assignments::overlap_size::<true>),
+			[(0, 4), (2, 1), (4, 1)]
+		);
+	}
+
+	#[test]
+	fn test_merge() {
+		let text = "2-4,6-8
+2-3,4-5
+5-7,7-9
+12-14,20-20
+6-6,4-6
+19-19,30-31";
+		let ranges = |exclusive_end| {
+			merge_assignments(
+				text.lines().map(std::string::ToString::to_string),
+				Assignments::parse::<false>,
+				exclusive_end,
+			)
+		};
+
+		// 2-9 is covered by the first three pairs, and 19-20 touch
+		assert_eq!(ranges(false), [(2, 10), (12, 15), (19, 21), (30, 32)]);
+		// 6-6 and 19-19 are now empty, and 12-14 no longer reaches 14
+		assert_eq!(ranges(true), [(2, 9), (12, 14), (30, 31)]);
+		assert_eq!(merge_ranges(std::iter::empty()), []);
+	}
+
+	#[test]
+	fn test_progress() {
+		let text = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+		let lines = || text.lines().map(std::string::ToString::to_string);
+		let pb = ProgressBar::hidden();
+
+		assert_eq!(
+			count_overlaps(
+				pb.wrap_iter(lines()),
+				Assignments::parse::<false>,
+				Assignments::overlaps_entirely::<false>,
+				false
+			),
+			count_overlaps(
+				lines(),
+				Assignments::parse::<false>,
+				Assignments::overlaps_entirely::<false>,
+				false
+			)
+		);
+		assert_eq!(pb.position(), 6);
+
+		// The flag doesn't take a value, so the mode after it is still the mode
+		let args = Args::try_parse_from(["overlapping-pairs", "--progress", "entire"]).unwrap();
+		assert!(args.progress.progress);
+		assert_eq!(args.progress.progress_ms, 100);
+		assert!(
+			Args::try_parse_from(["overlapping-pairs", "--progress-ms", "10", "entire"]).is_err()
+		);
+	}
+
+	#[test]
+	fn test_query() {
+		let text = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+		let pairs = || {
+			text.lines()
+				.map(|line| line.parse::<Assignments>().unwrap())
+		};
+
+		let index = PairIndex::new::<false, _>(pairs());
+		assert_eq!(index.count_overlapping(half_open::<false>((3, 7))), 6);
+		assert_eq!(index.count_overlapping(half_open::<false>((9, 9))), 1);
+		assert_eq!(index.count_overlapping(half_open::<false>((5, 5))), 5);
+		assert_eq!(index.count_overlapping(half_open::<false>((10, 20))), 0);
+
+		// 2-3,4-5 now ends before section 5, and 6-6 is now empty
+		let index = PairIndex::new::<true, _>(pairs());
+		assert_eq!(index.count_overlapping(half_open::<true>((5, 6))), 4);
+		assert_eq!(index.count_overlapping(half_open::<true>((3, 3))), 0);
+	}
 }
@@ -1,19 +1,21 @@
 #![feature(get_many_mut)]
 #![deny(clippy::pedantic)]
 use std::{
-	collections::VecDeque,
+	collections::{BTreeMap, VecDeque},
 	fs::File,
-	io::{self, BufRead},
-	path::{Path, PathBuf},
+	io::{self, BufRead, Write},
+	path::PathBuf,
 	str::FromStr,
+	thread,
 	time::Duration,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use clap::{Parser, ValueEnum};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, ValueEnum)]
 enum Mode {
@@ -23,60 +25,119 @@ enum Mode {
 	NoReverse,
 }
 
+#[derive(Clone, ValueEnum)]
+enum Format {
+	/// Print the contents of the top crate of every stack
+	Plain,
+	/// Print the initial stacks, the commands, and the final stacks as JSON
+	Json,
+}
+
 #[derive(Parser)]
 struct Args {
-	/// Input file path
+	/// Input file path. Use `-` to read the whole problem from stdin
 	#[arg(short, long, default_value = "input.txt")]
 	input_file: PathBuf,
 	/// What mode to run the program in
 	#[arg(value_enum)]
 	mode: Mode,
+	/// How to print the result of the simulation
+	#[arg(long, value_enum, default_value_t = Format::Plain)]
+	format: Format,
+	#[command(flatten)]
+	progress: Progress,
+	#[command(flatten)]
+	checks: Checks,
+	/// Also print the number of commands, and the total number of crates moved by them
+	#[arg(long)]
+	summary: bool,
+	/// Number stacks from 0 instead of 1 when printing commands and errors. The input file always numbers them from 1
+	#[arg(long)]
+	zero_based: bool,
+	/// Instead of printing the top crates, follow the crate with this label through the simulation and print which stack
+	/// it's in after every command. The label must only be on one crate
+	#[arg(long, value_name = "LABEL", conflicts_with_all = ["format", "animate", "summary", "verify"])]
+	track: Option<char>,
 }
 
-/// Do a cursory parse through the lines of the input file, and find out the number of stacks,
-/// the largest initial size of a stack, and how many commands there will be to process.
-/// Assumes at most 9 stacks.
-fn get_num_stacks_and_stack_size<T: Iterator<Item = String>>(
-	mut lines: T,
-) -> (usize, usize, usize) {
-	// Figure out how many stacks there are and a good initial size for the stacks
-	// by first finding the bottom line of the initial stack setup. This line
-	// tells us how many stacks there are, and how many lines before it tells us how large
-	// these stacks need to be to fit the initial setup.
-	let mut num_stacks = 0;
-	let stack_size = lines
-		.by_ref()
-		.take_while(|line| {
-			if line.starts_with(" 1") {
-				num_stacks = line.bytes().skip(1).step_by(4).count();
-				false
-			} else {
-				true
-			}
-		})
-		.count();
+#[derive(clap::Args)]
+struct Progress {
+	/// Animate the simulation in the terminal, redrawing the stacks after every command
+	#[arg(long)]
+	animate: bool,
+	/// How long to wait after each command when animating, in milliseconds
+	#[arg(long, default_value_t = 100)]
+	delay: u64,
+	/// Don't show the progress bar. It's drawn on stderr, so it never gets mixed into the results anyway
+	#[arg(long, conflicts_with = "animate")]
+	quiet: bool,
+}
 
-	// The remaining lines (except for a blank one) are all commands to process
-	let num_commands = lines.skip(1).count();
+#[derive(clap::Args)]
+struct Checks {
+	/// Fail on malformed commands instead of skipping them. Blank lines and `#` comments are always skipped
+	#[arg(long)]
+	strict: bool,
+	/// Fail if the crates at the end of the simulation aren't the same as the crates at the start
+	#[arg(long)]
+	verify: bool,
+	/// Instead of simulating, only check that every command can be carried out, given how many crates are on each stack.
+	/// Reports every command which can't be, and fails if there are any
+	#[arg(long, conflicts_with_all = ["format", "animate", "track", "verify", "summary"])]
+	check: bool,
+}
+
+/// Read the header of the input file - the initial stack setup, up to and including the line numbering the stacks.
+/// The lines are buffered so that they can be looked over more than once, and the remaining lines of the input
+/// are left in `lines` to be read later.
+fn read_header<T: Iterator<Item = String>>(lines: &mut T) -> Vec<String> {
+	let mut header = Vec::new();
+
+	for line in lines {
+		// The numbering line is the bottom of the initial stack setup
+		let is_footer = line.starts_with(" 1");
+		header.push(line);
+
+		if is_footer {
+			break;
+		}
+	}
+
+	header
+}
 
-	(num_stacks, stack_size, num_commands)
+/// Find out the number of stacks and the largest initial size of a stack from the header of the input file.
+/// Assumes at most 9 stacks.
+fn get_num_stacks_and_stack_size(header: &[String]) -> (usize, usize) {
+	// The bottom line of the initial stack setup tells us how many stacks there are,
+	// and how many lines of crates before it tells us how large these stacks need to be to fit the initial setup.
+	// Blank lines between the crates and the bottom line don't hold any crates, so don't count them.
+	match header.split_last() {
+		Some((footer, stack_lines)) => (
+			footer.bytes().skip(1).step_by(4).count(),
+			stack_lines
+				.iter()
+				.filter(|line| !line.trim().is_empty())
+				.count(),
+		),
+		None => (0, 0),
+	}
 }
 
-/// Parse the first half of the input file into stacks
-fn get_initial_stacks<T: Iterator<Item = String>>(
-	lines: &mut T,
+/// Parse the header of the input file into stacks
+fn get_initial_stacks(
+	header: &[String],
 	num_stacks: usize,
 	stack_size: usize,
 ) -> Vec<VecDeque<u8>> {
-	// Create our stacks
+	// Create our stacks. `stack_size` is only a hint - stacks can still grow if there are more crates than expected
 	let mut stacks: Vec<_> = vec![VecDeque::with_capacity(stack_size); num_stacks];
 
-	// Add on to the stacks for each line in the initial stack setup
-	lines
-		// We want to continue reading lines after reading the initial setup of the stacks, so take by reference
-		.by_ref()
-		// Read the stack setup. We know how many lines there are here, because we counted them in stack_size
-		.take(stack_size)
+	// Add on to the stacks for each line in the initial stack setup, which is every line of the header above the footer
+	header
+		.split_last()
+		.map_or(&[][..], |(_footer, stack_lines)| stack_lines)
+		.iter()
 		// For each line, add the crate contents to the corresponding stack
 		.for_each(|line| {
 			let contents = line.bytes().skip(1).step_by(4);
@@ -96,7 +157,78 @@ fn get_initial_stacks<T: Iterator<Item = String>>(
 	stacks
 }
 
-#[derive(Debug)]
+/// Parse the whole input in a single pass, returning the initial stacks and the lines containing commands to simulate.
+fn parse_input<R: BufRead>(reader: R) -> (Vec<VecDeque<u8>>, Vec<String>) {
+	let mut lines = reader
+		.lines()
+		// Skip lines which couldn't be read
+		.flatten();
+
+	let header = read_header(&mut lines);
+	let (num_stacks, stack_size) = get_num_stacks_and_stack_size(&header);
+	let stacks = get_initial_stacks(&header, num_stacks, stack_size);
+
+	// Skip the blank line between the stack setup and the commands.
+	// The remaining lines are all commands to process.
+	let commands = lines.skip(1).collect();
+
+	(stacks, commands)
+}
+
+/// Remove blank lines and `#` comments from the commands section of the input file.
+/// If `strict`, every other line must be a valid command - otherwise malformed commands are left to be skipped during simulation.
+fn filter_commands(commands: Vec<String>, strict: bool) -> Result<Vec<String>> {
+	let commands = commands
+		.into_iter()
+		.filter(|line| {
+			let line = line.trim();
+			!line.is_empty() && !line.starts_with('#')
+		})
+		.collect::<Vec<_>>();
+
+	if strict {
+		if let Some((i, err)) = commands
+			.iter()
+			.enumerate()
+			.find_map(|(i, line)| line.parse::<Command>().err().map(|err| (i, err)))
+		{
+			return Err(anyhow!("Command {}: {err}", i + 1));
+		}
+	}
+
+	Ok(commands)
+}
+
+#[derive(Clone, Copy)]
+/// How stacks are numbered when they're shown. Stacks are always numbered from 0 internally.
+enum IndexBase {
+	/// Numbered from 0, the same as internally
+	Zero,
+	/// Numbered from 1, the same as in the input file
+	One,
+}
+
+impl IndexBase {
+	/// The first stack number
+	fn first(self) -> usize {
+		match self {
+			IndexBase::Zero => 0,
+			IndexBase::One => 1,
+		}
+	}
+
+	/// Convert a stack number in this base into an internal index, or `None` if it comes before the first stack
+	fn to_index(self, number: usize) -> Option<usize> {
+		number.checked_sub(self.first())
+	}
+
+	/// Convert an internal index into a stack number in this base
+	fn to_number(self, index: usize) -> usize {
+		index + self.first()
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 /// Struct epresenting a single move command a la 'move 1 from 2 to 1'
 struct Command {
 	/// How many crates to move
@@ -120,87 +252,570 @@ impl FromStr for Command {
 		// Each number above is captured in a capture group - use those to parse
 		let captures = REGEX
 			.captures(text)
-			.unwrap_or_else(|| panic!("Command `{text}` doesn't match regex"));
+			.ok_or_else(|| anyhow!("Command `{text}` doesn't match regex"))?;
+
+		// The input file numbers stacks from 1
+		let stack = |name: &str| -> Result<usize> {
+			let number = captures[name].parse()?;
+			IndexBase::One
+				.to_index(number)
+				.ok_or_else(|| anyhow!("There is no stack {number} in `{text}`"))
+		};
 
 		Ok(Command {
 			num_moved: captures["num_moved"].parse()?,
-			stack_from: captures["from_stack"].parse::<usize>()? - 1,
-			stack_to: captures["to_stack"].parse::<usize>()? - 1,
+			stack_from: stack("from_stack")?,
+			stack_to: stack("to_stack")?,
+		})
+	}
+}
+
+impl Command {
+	/// The command as it would be written in the input file, with stacks numbered in `base`
+	fn describe(&self, base: IndexBase) -> String {
+		format!(
+			"move {} from {} to {}",
+			self.num_moved,
+			base.to_number(self.stack_from),
+			base.to_number(self.stack_to)
+		)
+	}
+
+	/// The same command with its stacks numbered in `base` instead of internally, for printing
+	fn numbered(self, base: IndexBase) -> Self {
+		Self {
+			stack_from: base.to_number(self.stack_from),
+			stack_to: base.to_number(self.stack_to),
+			..self
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// A command which can't be carried out. `number` is which command it is, counting from 1.
+enum SimulationError {
+	/// The command moves crates from or to a stack which doesn't exist
+	NoSuchStack {
+		number: usize,
+		command: Command,
+		stack: usize,
+	},
+	/// The command moves more crates than there are on the stack it moves them from
+	TooFewCrates {
+		number: usize,
+		command: Command,
+		available: usize,
+	},
+}
+
+impl SimulationError {
+	/// Describe what went wrong, with stacks numbered in `base`
+	fn describe(&self, base: IndexBase) -> String {
+		match self {
+			SimulationError::NoSuchStack {
+				number,
+				command,
+				stack,
+			} => format!(
+				"Command {number} (`{}`): There is no stack {}",
+				command.describe(base),
+				base.to_number(*stack)
+			),
+			SimulationError::TooFewCrates {
+				number,
+				command,
+				available,
+			} => format!(
+				"Command {number} (`{}`): Stack {} only has {available} crates",
+				command.describe(base),
+				base.to_number(command.stack_from)
+			),
+		}
+	}
+}
+
+/// Count the number of commands, and the total number of crates moved across all of them
+fn summary<S: AsRef<str>, T: Iterator<Item = S>>(lines: T) -> (usize, usize) {
+	lines
+		.flat_map(|line| line.as_ref().parse::<Command>())
+		.fold((0, 0), |(commands, crates), command| {
+			(commands + 1, crates + command.num_moved)
+		})
+}
+
+/// Check every command against the number of crates on each stack, without moving any crates.
+/// Commands which can't be carried out are skipped, as if they weren't there, and every one of them is returned.
+fn check_commands<S: AsRef<str>, T: Iterator<Item = S>>(
+	lines: T,
+	stacks: &[VecDeque<u8>],
+) -> Vec<SimulationError> {
+	// Which crates are moved doesn't matter, only how many there are on each stack
+	let mut sizes = stacks.iter().map(VecDeque::len).collect::<Vec<_>>();
+
+	lines
+		.flat_map(|line| line.as_ref().parse::<Command>())
+		.enumerate()
+		.filter_map(|(i, command)| {
+			let number = i + 1;
+			let no_such_stack = |stack| SimulationError::NoSuchStack {
+				number,
+				command,
+				stack,
+			};
+
+			// Same checks as Simulation::step
+			if command.stack_to >= sizes.len() {
+				return Some(no_such_stack(command.stack_to));
+			}
+			let Some(&available) = sizes.get(command.stack_from) else {
+				return Some(no_such_stack(command.stack_from));
+			};
+			if available < command.num_moved {
+				return Some(SimulationError::TooFewCrates {
+					number,
+					command,
+					available,
+				});
+			}
+
+			sizes[command.stack_from] -= command.num_moved;
+			sizes[command.stack_to] += command.num_moved;
+
+			None
 		})
+		.collect()
+}
+
+/// Write every command which can't be carried out, as found by [`check_commands`], with stacks numbered in `base`.
+/// Fails if there were any.
+fn dry_run<W: Write, S: AsRef<str>, T: Iterator<Item = S>>(
+	out: &mut W,
+	lines: T,
+	stacks: &[VecDeque<u8>],
+	base: IndexBase,
+) -> Result<()> {
+	let errors = check_commands(lines, stacks);
+
+	for err in &errors {
+		writeln!(out, "{}", err.describe(base))?;
 	}
+
+	// Exit non-zero if there was anything to report
+	if errors.is_empty() {
+		writeln!(out, "Every command can be carried out")?;
+		Ok(())
+	} else {
+		Err(anyhow!(
+			"Found {} commands which can't be carried out",
+			errors.len()
+		))
+	}
+}
+
+/// The state of a simulation of a crane moving crates between stacks, which can be advanced one command at a time.
+/// `REVERSE` is whether the crane reverses the order of the crates it moves, as in the first variant of the problem.
+struct Simulation<const REVERSE: bool> {
+	stacks: Vec<VecDeque<u8>>,
+	/// The number of commands executed so far
+	steps: usize,
+}
+
+impl<const REVERSE: bool> Simulation<REVERSE> {
+	fn new(stacks: Vec<VecDeque<u8>>) -> Self {
+		Self { stacks, steps: 0 }
+	}
+
+	/// Execute a single command. Fails without changing any stacks if the command can't be carried out.
+	fn step(&mut self, command: &Command) -> Result<(), SimulationError> {
+		let number = self.steps + 1;
+		let no_such_stack = |stack| SimulationError::NoSuchStack {
+			number,
+			command: *command,
+			stack,
+		};
+
+		if command.stack_to >= self.stacks.len() {
+			return Err(no_such_stack(command.stack_to));
+		}
+		let stack_from = self
+			.stacks
+			.get_mut(command.stack_from)
+			.ok_or_else(|| no_such_stack(command.stack_from))?;
+		if stack_from.len() < command.num_moved {
+			return Err(SimulationError::TooFewCrates {
+				number,
+				command: *command,
+				available: stack_from.len(),
+			});
+		}
+
+		// Split off all of the grabbed crates
+		let mut temp = stack_from.split_off(stack_from.len() - command.num_moved);
+
+		// If it's the CrateMover 9000, we need to reverse this stack before putting on the next stack
+		if REVERSE {
+			temp.make_contiguous().reverse();
+		}
+
+		let stack_to = &mut self.stacks[command.stack_to];
+		stack_to.append(&mut temp);
+		self.steps += 1;
+
+		Ok(())
+	}
+
+	/// Finish the simulation, returning the final stacks
+	fn into_stacks(self) -> Vec<VecDeque<u8>> {
+		self.stacks
+	}
+}
+
+/// The contents of the top crate of every stack, as a string. Empty stacks are shown as a space.
+fn top_crates(stacks: &[VecDeque<u8>]) -> String {
+	stacks
+		.iter()
+		.map(|stack| stack.back().map_or(' ', |c| char::from(*c)))
+		.collect()
 }
 
 /// Simulate all of the commands in the input file, with the given initial state of stacks.
-/// Returns the contents of the top crate of all of the stacks at the end of the simulation
+/// Returns the stacks at the end of the simulation, or the first command which couldn't be carried out
 fn simulate<const REVERSE: bool, T: Iterator<Item = String>>(
 	lines: T,
-	mut stacks: Vec<VecDeque<u8>>,
-) -> impl Iterator<Item = u8> {
+	stacks: Vec<VecDeque<u8>>,
+) -> Result<Vec<VecDeque<u8>>, SimulationError> {
+	let mut simulation = Simulation::<REVERSE>::new(stacks);
+
 	lines
 		// Parse each line as a command
 		.flat_map(|line| line.parse::<Command>())
 		// Execute the command
-		.for_each(|command| {
-			let stack_from = &mut stacks[command.stack_from];
-			// Split off all of the grabbed crates
-			let mut temp = stack_from.split_off(stack_from.len() - command.num_moved);
-
-			// If it's the CrateMover 9000, we need to reverse this stack before putting on the next stack
-			if REVERSE {
-				temp.make_contiguous().reverse();
-			}
+		.try_for_each(|command| simulation.step(&command))?;
 
-			let stack_to = &mut stacks[command.stack_to];
-			stack_to.append(&mut temp);
+	Ok(simulation.into_stacks())
+}
+
+/// Follow the crate labelled `label` through all of the commands in the input file, as in [`simulate`].
+/// Returns the (0-based) stack the crate is in and how far up that stack it is (counting from 0 at the bottom),
+/// first at the start and then after each command. Fails if there isn't exactly one crate with the label.
+fn track<const REVERSE: bool, T: Iterator<Item = String>>(
+	lines: T,
+	stacks: Vec<VecDeque<u8>>,
+	label: u8,
+	base: IndexBase,
+) -> Result<Vec<(usize, usize)>> {
+	let mut found = stacks.iter().enumerate().flat_map(|(i, stack)| {
+		stack
+			.iter()
+			.enumerate()
+			.filter(|(_, c)| **c == label)
+			.map(move |(height, _)| (i, height))
+	});
+	let start = found
+		.next()
+		.ok_or_else(|| anyhow!("There is no crate `{}`", char::from(label)))?;
+	if found.next().is_some() {
+		bail!(
+			"There is more than one crate `{}`, so it can't be tracked",
+			char::from(label)
+		);
+	}
+
+	let mut simulation = Simulation::<REVERSE>::new(stacks);
+	let mut positions = vec![start];
+	for command in lines.flat_map(|line| line.parse::<Command>()) {
+		let (stack, height) = *positions.last().unwrap();
+		let from_len = simulation
+			.stacks
+			.get(command.stack_from)
+			.map_or(0, VecDeque::len);
+		simulation
+			.step(&command)
+			.map_err(|err| anyhow!(err.describe(base)))?;
+
+		// The crate only moves if it's one of the crates grabbed off the top of its stack
+		let grabbed = from_len - command.num_moved;
+		positions.push(if stack == command.stack_from && height >= grabbed {
+			// How far up the grabbed crates it is, which is flipped if they're reversed
+			let offset = if REVERSE {
+				command.num_moved - 1 - (height - grabbed)
+			} else {
+				height - grabbed
+			};
+			let to_len = simulation.stacks[command.stack_to].len();
+
+			(command.stack_to, to_len - command.num_moved + offset)
+		} else {
+			(stack, height)
 		});
+	}
 
-	// Return the tops of all the crates
-	stacks.into_iter().map(|stack| *stack.back().unwrap())
+	Ok(positions)
 }
 
-fn lines_reader<P: AsRef<Path>>(p: P) -> Result<impl Iterator<Item = String>> {
-	let file = File::open(p)?;
-	Ok(io::BufReader::with_capacity(10_000_000, file)
-		.lines()
-		// Skip lines which couldn't be read
-		.flatten())
+/// Print where a tracked crate is at the start and after each command, as found by [`track`]
+fn print_track(positions: &[(usize, usize)], label: char, base: IndexBase) {
+	let (&(stack, _), moves) = positions.split_first().unwrap();
+	println!("Crate {label} starts in stack {}", base.to_number(stack));
+
+	for (i, (stack, _)) in moves.iter().enumerate() {
+		println!("Command {}: stack {}", i + 1, base.to_number(*stack));
+	}
+
+	let (stack, height) = positions.last().unwrap();
+	println!(
+		"Crate {label} ends up in stack {}, {} crates up from the bottom",
+		base.to_number(*stack),
+		height + 1
+	);
 }
 
-fn main() -> Result<()> {
-	let args = Args::parse();
+#[derive(Serialize, Deserialize)]
+/// The whole simulation, for printing as JSON. Each stack is a string of crates from the bottom up,
+/// and the stacks in each command are numbered as in the [`IndexBase`] the report was made with.
+struct SimulationReport {
+	initial_stacks: Vec<String>,
+	commands: Vec<Command>,
+	final_stacks: Vec<String>,
+	/// The top crate of every final stack, as would be printed without JSON
+	top: String,
+}
 
-	let lines = lines_reader(&args.input_file)?;
-	let (num_stacks, stack_size, num_commands) = get_num_stacks_and_stack_size(lines);
+/// Convert stacks of crates into strings, from the bottom of each stack up
+fn stacks_to_strings(stacks: &[VecDeque<u8>]) -> Vec<String> {
+	stacks
+		.iter()
+		.map(|stack| stack.iter().copied().map(char::from).collect())
+		.collect()
+}
 
-	let mut lines = lines_reader(&args.input_file)?;
-	let stacks = get_initial_stacks(&mut lines, num_stacks, stack_size);
+/// The same as [`simulate`], but keeps track of the initial stacks, all of the commands, and the final stacks
+fn simulation_report<const REVERSE: bool, T: Iterator<Item = String>>(
+	lines: T,
+	stacks: Vec<VecDeque<u8>>,
+	base: IndexBase,
+) -> Result<SimulationReport, SimulationError> {
+	let initial_stacks = stacks_to_strings(&stacks);
+	let commands = lines
+		.flat_map(|line| line.parse::<Command>())
+		.collect::<Vec<_>>();
 
-	// Skip the number line and blank line in the instructions
-	let lines = lines.skip(2);
+	let mut simulation = Simulation::<REVERSE>::new(stacks);
+	for command in &commands {
+		simulation.step(command)?;
+	}
 
-	// Progress bar
-	let pb =
-		ProgressBar::new(num_commands as u64)
-			.with_style(
-				ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} ({eta})")
-					.unwrap()
-					.progress_chars("#>-")
-			);
+	Ok(SimulationReport {
+		initial_stacks,
+		commands: commands
+			.into_iter()
+			.map(|command| command.numbered(base))
+			.collect(),
+		final_stacks: stacks_to_strings(&simulation.stacks),
+		top: top_crates(&simulation.stacks),
+	})
+}
+
+/// Count how many of each crate there are
+fn crate_counts<T: Iterator<Item = u8>>(crates: T) -> BTreeMap<u8, usize> {
+	crates.fold(BTreeMap::new(), |mut counts, c| {
+		*counts.entry(c).or_default() += 1;
+		counts
+	})
+}
+
+/// Check that no crates have been lost or duplicated, given the [`crate_counts`] before and after simulating
+fn verify_conservation(before: &BTreeMap<u8, usize>, after: &BTreeMap<u8, usize>) -> Result<()> {
+	// Any crate which has been lost or duplicated shows up in at least one of the counts with a different number in the other
+	if let Some(c) = before
+		.keys()
+		.chain(after.keys())
+		.find(|c| before.get(c) != after.get(c))
+	{
+		bail!(
+			"Crate `{}`: {} before simulating, but {} after",
+			char::from(*c),
+			before.get(c).unwrap_or(&0),
+			after.get(c).unwrap_or(&0)
+		);
+	}
+
+	Ok(())
+}
+
+/// Draw the stacks the same way as they are drawn in the input file, with the stack numbers along the bottom
+fn render_stacks(stacks: &[VecDeque<u8>]) -> String {
+	let height = stacks.iter().map(VecDeque::len).max().unwrap_or(0);
+
+	// Draw from the top down, leaving a blank space for stacks which aren't tall enough to reach this row
+	let mut rows = (0..height)
+		.rev()
+		.map(|row| {
+			stacks
+				.iter()
+				.map(|stack| match stack.get(row) {
+					Some(c) => format!("[{}]", char::from(*c)),
+					None => "   ".to_string(),
+				})
+				.collect::<Vec<_>>()
+				.join(" ")
+		})
+		.collect::<Vec<_>>();
+
+	rows.push(
+		(1..=stacks.len())
+			.map(|i| format!(" {i} "))
+			.collect::<Vec<_>>()
+			.join(" "),
+	);
+
+	rows.join("\n")
+}
+
+/// The same as [`simulate`], but redraws the stacks as the message of a progress bar after each command,
+/// waiting for `delay` in between so that the crates can be seen moving.
+fn animate<const REVERSE: bool, T: Iterator<Item = String>>(
+	lines: T,
+	stacks: Vec<VecDeque<u8>>,
+	pb: &ProgressBar,
+	delay: Duration,
+) -> Result<Vec<VecDeque<u8>>, SimulationError> {
+	let mut simulation = Simulation::<REVERSE>::new(stacks);
+	pb.set_message(render_stacks(&simulation.stacks));
+
+	lines
+		.flat_map(|line| line.parse::<Command>())
+		.try_for_each(|command| {
+			simulation.step(&command)?;
+			pb.set_message(render_stacks(&simulation.stacks));
+			thread::sleep(delay);
+
+			Ok(())
+		})?;
+
+	Ok(simulation.into_stacks())
+}
+
+/// A progress bar for simulating `len` commands, drawn on stderr so that it stays out of the results.
+/// When animating, the stacks are drawn above the bar as its message. Hidden if `quiet`.
+fn progress_bar(len: usize, animate: bool, quiet: bool) -> ProgressBar {
+	if quiet {
+		return ProgressBar::hidden();
+	}
+
+	let template = if animate {
+		"{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} ({eta})"
+	} else {
+		"{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {human_pos}/{human_len} ({eta})"
+	};
+	let pb = ProgressBar::with_draw_target(Some(len as u64), ProgressDrawTarget::stderr())
+		.with_style(
+			ProgressStyle::with_template(template)
+				.unwrap()
+				.progress_chars("#>-"),
+		);
 	// Don't update progress bar every time we simulate a command. Instead do it every .1 second.
 	pb.enable_steady_tick(Duration::from_millis(100));
 
-	// Add progress bar to iterator
-	let lines = pb.wrap_iter(lines);
+	pb
+}
+
+/// Write the top crate of every stack, followed by the number of commands and crates moved if there's a summary
+fn write_result<W: Write>(
+	out: &mut W,
+	stacks: &[VecDeque<u8>],
+	summary: Option<(usize, usize)>,
+) -> io::Result<()> {
+	writeln!(out, "{}", top_crates(stacks))?;
+
+	if let Some((commands, crates)) = summary {
+		writeln!(out, "Commands: {commands}")?;
+		writeln!(out, "Crates moved: {crates}")?;
+	}
 
-	let tops = match args.mode {
-		Mode::Reverse => simulate::<true, _>(lines, stacks).collect::<Vec<_>>(),
-		Mode::NoReverse => simulate::<false, _>(lines, stacks).collect::<Vec<_>>(),
+	Ok(())
+}
+
+fn main() -> Result<()> {
+	let args = Args::parse();
+
+	// The input is only read once, so it can come from stdin just as well as from a file
+	let (stacks, commands) = if args.input_file.as_os_str() == "-" {
+		parse_input(io::stdin().lock())
+	} else {
+		let file = File::open(args.input_file)?;
+		parse_input(io::BufReader::with_capacity(10_000_000, file))
 	};
+	let commands = filter_commands(commands, args.checks.strict)?;
+	let base = if args.zero_based {
+		IndexBase::Zero
+	} else {
+		IndexBase::One
+	};
+	if args.checks.check {
+		return dry_run(&mut io::stdout().lock(), commands.iter(), &stacks, base);
+	}
 
-	// Convert to string for pretty printing
-	let top = String::from_utf8_lossy(&tops);
+	// Independent of how the crates are moved, so it can be worked out before simulating
+	let summary = args.summary.then(|| summary(commands.iter()));
+
+	let pb = progress_bar(commands.len(), args.progress.animate, args.progress.quiet);
+
+	// Add progress bar to iterator
+	let lines = pb.wrap_iter(commands.into_iter());
+
+	// Only the counts are needed to verify the simulation, so count before the stacks are consumed by it
+	let before = args
+		.checks
+		.verify
+		.then(|| crate_counts(stacks.iter().flatten().copied()));
+
+	if let Format::Json = args.format {
+		let report = match args.mode {
+			Mode::Reverse => simulation_report::<true, _>(lines, stacks, base),
+			Mode::NoReverse => simulation_report::<false, _>(lines, stacks, base),
+		}
+		.map_err(|err| anyhow!(err.describe(base)))?;
+
+		if let Some(before) = &before {
+			let after = crate_counts(report.final_stacks.iter().flat_map(|stack| stack.bytes()));
+			verify_conservation(before, &after)?;
+		}
 
-	println!("{top}");
+		println!("{}", serde_json::to_string(&report)?);
+
+		return Ok(());
+	}
+
+	if let Some(label) = args.track {
+		// Crates are single bytes, so a label which isn't can't be on any crate
+		let byte = u8::try_from(label).map_err(|_| anyhow!("There is no crate `{label}`"))?;
+		let positions = match args.mode {
+			Mode::Reverse => track::<true, _>(lines, stacks, byte, base),
+			Mode::NoReverse => track::<false, _>(lines, stacks, byte, base),
+		}?;
+		pb.finish_and_clear();
+
+		print_track(&positions, label, base);
+
+		return Ok(());
+	}
+
+	let delay = Duration::from_millis(args.progress.delay);
+	let animating = args.progress.animate;
+	let stacks = match args.mode {
+		Mode::Reverse if animating => animate::<true, _>(lines, stacks, &pb, delay),
+		Mode::NoReverse if animating => animate::<false, _>(lines, stacks, &pb, delay),
+		Mode::Reverse => simulate::<true, _>(lines, stacks),
+		Mode::NoReverse => simulate::<false, _>(lines, stacks),
+	}
+	.map_err(|err| anyhow!(err.describe(base)))?;
+
+	if let Some(before) = &before {
+		verify_conservation(before, &crate_counts(stacks.iter().flatten().copied()))?;
+	}
+
+	write_result(&mut io::stdout().lock(), &stacks, summary)?;
 
 	Ok(())
 }
@@ -248,15 +863,12 @@ move 1 from 1 to 2";
 			.map(std::string::ToString::to_string)
 			.collect();
 
-		let (num_stacks, stack_size, num_commands) =
-			get_num_stacks_and_stack_size(lines.clone().into_iter());
-
-		let mut lines = lines.into_iter();
-		let mut stacks = get_initial_stacks(&mut lines, num_stacks, stack_size);
+		let header = read_header(&mut lines.into_iter());
+		let (num_stacks, stack_size) = get_num_stacks_and_stack_size(&header);
+		let mut stacks = get_initial_stacks(&header, num_stacks, stack_size);
 
 		assert_eq!(num_stacks, 3);
 		assert_eq!(stack_size, 3);
-		assert_eq!(num_commands, 4);
 
 		macro_rules! test_stack {
 			($idx:expr, $str:expr) => {
@@ -271,30 +883,247 @@ move 1 from 1 to 2";
 		test_stack!(3, "P");
 	}
 
+	#[test]
+	fn blank_line_before_footer() {
+		let example = EXAMPLE.replacen("\n 1 ", "\n\n 1 ", 1);
+		let header = read_header(&mut example.lines().map(std::string::ToString::to_string));
+		let (num_stacks, stack_size) = get_num_stacks_and_stack_size(&header);
+
+		assert_eq!(header.len(), 5);
+		assert_eq!((num_stacks, stack_size), (3, 3));
+
+		// Stacks still come out the same, even if told to expect shorter ones
+		let mut stacks = get_initial_stacks(&header, num_stacks, 1);
+		assert_eq!(String::from_utf8_lossy(stacks[1].make_contiguous()), "MCD");
+
+		let (stacks, commands) = parse_input(std::io::Cursor::new(example));
+		let stacks = simulate::<true, _>(commands.into_iter(), stacks).unwrap();
+		assert_eq!(top_crates(&stacks), "CMZ");
+	}
+
+	#[test]
+	fn single_pass_parse() {
+		let (mut stacks, commands) = parse_input(std::io::Cursor::new(EXAMPLE));
+
+		assert_eq!(commands.len(), 4);
+		assert_eq!(commands[0], "move 1 from 2 to 1");
+		assert_eq!(String::from_utf8_lossy(stacks[1].make_contiguous()), "MCD");
+
+		let stacks = simulate::<true, _>(commands.into_iter(), stacks).unwrap();
+		assert_eq!(top_crates(&stacks), "CMZ");
+	}
+
+	#[test]
+	fn combined_stream() {
+		// Everything comes through one reader, the same as piping the whole problem through stdin
+		let reader: Box<dyn BufRead> = Box::new(std::io::Cursor::new(EXAMPLE.as_bytes().to_vec()));
+		let (stacks, commands) = parse_input(reader);
+
+		let stacks = simulate::<true, _>(commands.into_iter(), stacks).unwrap();
+		assert_eq!(top_crates(&stacks), "CMZ");
+	}
+
 	#[test]
 	fn test_simulate() {
-		let lines: Vec<_> = EXAMPLE
-			.lines()
-			.map(std::string::ToString::to_string)
-			.collect();
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		let lines = lines.into_iter();
+
+		let final_stacks = simulate::<true, _>(lines.clone(), stacks.clone()).unwrap();
+		assert_eq!(top_crates(&final_stacks), "CMZ");
+
+		let final_stacks = simulate::<false, _>(lines, stacks).unwrap();
+		assert_eq!(top_crates(&final_stacks), "MCD");
+	}
+
+	#[test]
+	fn test_animate() {
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		let pb = ProgressBar::hidden();
+
+		assert_eq!(
+			render_stacks(&stacks),
+			"    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 "
+		);
+
+		let stacks = animate::<true, _>(lines.into_iter(), stacks, &pb, Duration::ZERO).unwrap();
+		assert_eq!(top_crates(&stacks), "CMZ");
+	}
+
+	#[test]
+	fn tracking() {
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		let lines = lines.into_iter();
+
+		// `D` is moved onto stack 1, then flipped over to second from the bottom of stack 3, where it stays
+		assert_eq!(
+			track::<true, _>(lines.clone(), stacks.clone(), b'D', IndexBase::One).unwrap(),
+			[(1, 2), (0, 2), (2, 1), (2, 1), (2, 1)]
+		);
+		// Without flipping, it ends up at the top of stack 3 instead
+		assert_eq!(
+			track::<false, _>(lines.clone(), stacks.clone(), b'D', IndexBase::One).unwrap(),
+			[(1, 2), (0, 2), (2, 3), (2, 3), (2, 3)]
+		);
+
+		assert!(track::<true, _>(lines.clone(), stacks.clone(), b'X', IndexBase::One).is_err());
+		let mut stacks = stacks;
+		stacks[2].push_back(b'D');
+		assert!(track::<true, _>(lines, stacks, b'D', IndexBase::One).is_err());
+	}
+
+	#[test]
+	fn quiet() {
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		let pb = progress_bar(lines.len(), false, true);
+		assert!(pb.is_hidden());
 
-		let (num_stacks, stack_size, _num_commands) =
-			get_num_stacks_and_stack_size(lines.clone().into_iter());
+		let stacks = simulate::<true, _>(pb.wrap_iter(lines.into_iter()), stacks).unwrap();
+		let mut out = Vec::new();
+		write_result(&mut out, &stacks, None).unwrap();
+		assert_eq!(String::from_utf8(out).unwrap(), "CMZ\n");
+	}
+
+	#[test]
+	fn tops_and_stacks() {
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		let stacks = simulate::<true, _>(lines.into_iter(), stacks).unwrap();
+
+		// Neither of these consume the stacks, so they can both be used on the same final stacks
+		assert_eq!(top_crates(&stacks), "CMZ");
+		assert_eq!(
+			render_stacks(&stacks),
+			"        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3 "
+		);
+		assert_eq!(stacks_to_strings(&stacks), ["C", "M", "PDNZ"]);
+
+		// Empty stacks don't have a top crate
+		assert_eq!(top_crates(&[VecDeque::from(*b"A"), VecDeque::new()]), "A ");
+	}
+
+	#[test]
+	fn json_report() {
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		let report =
+			simulation_report::<true, _>(lines.into_iter(), stacks, IndexBase::One).unwrap();
 
-		let mut lines = lines.into_iter();
-		let stacks = get_initial_stacks(&mut lines, num_stacks, stack_size);
+		let json = serde_json::to_string(&report).unwrap();
+		let report: SimulationReport = serde_json::from_str(&json).unwrap();
 
-		// Skip the number line and blank line in the instructions
-		let lines = lines.skip(2);
+		assert_eq!(report.initial_stacks, ["ZN", "MCD", "P"]);
+		assert_eq!(report.commands.len(), 4);
+		// Stacks are numbered the same as in the input file
+		assert_eq!(
+			report.commands[0].describe(IndexBase::Zero),
+			"move 1 from 2 to 1"
+		);
+		assert_eq!(report.final_stacks, ["C", "M", "PDNZ"]);
+		assert_eq!(report.top, "CMZ");
+	}
+
+	#[test]
+	fn comments() {
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		let lines = lines
+			.into_iter()
+			.flat_map(|line| ["# a comment".to_string(), String::new(), line])
+			.collect::<Vec<_>>();
+
+		let commands = filter_commands(lines.clone(), true).unwrap();
+		assert_eq!(commands.len(), 4);
 
-		let tops = simulate::<true, _>(lines.clone(), stacks.clone()).collect::<Vec<_>>();
-		let top = String::from_utf8_lossy(&tops);
+		let stacks = simulate::<true, _>(commands.into_iter(), stacks).unwrap();
+		assert_eq!(top_crates(&stacks), "CMZ");
+
+		// Malformed commands are only an error when strict
+		let mut lines = lines;
+		lines.push("move one from 2 to 1".to_string());
+		assert_eq!(filter_commands(lines.clone(), false).unwrap().len(), 5);
+		assert!(filter_commands(lines, true).is_err());
+	}
+
+	#[test]
+	fn test_summary() {
+		let (_, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+
+		assert_eq!(summary(lines.iter()), (4, 7));
+	}
+
+	#[test]
+	fn conservation() {
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		let before = crate_counts(stacks.iter().flatten().copied());
+
+		let mut stacks = simulate::<true, _>(lines.into_iter(), stacks).unwrap();
+		verify_conservation(&before, &crate_counts(stacks.iter().flatten().copied())).unwrap();
+
+		// A broken simulation which duplicates a crate
+		stacks[0].push_back(b'Z');
+		assert!(
+			verify_conservation(&before, &crate_counts(stacks.iter().flatten().copied())).is_err()
+		);
+
+		// A broken simulation which loses crates
+		stacks[0].clear();
+		assert!(
+			verify_conservation(&before, &crate_counts(stacks.iter().flatten().copied())).is_err()
+		);
+	}
+
+	#[test]
+	fn dry_run_check() {
+		let (stacks, lines) = parse_input(std::io::Cursor::new(EXAMPLE));
+		assert!(check_commands(lines.iter(), &stacks).is_empty());
+
+		// The second command moves 3 crates from stack 2, which only has 2 by then, so it's skipped.
+		// The third command then still has enough crates, but the last one moves from a stack which doesn't exist
+		let lines = [
+			"move 1 from 2 to 1",
+			"move 3 from 2 to 3",
+			"move 2 from 2 to 1",
+			"move 1 from 4 to 1",
+		];
+		let errors = check_commands(lines.iter(), &stacks);
+		assert_eq!(
+			errors
+				.iter()
+				.map(|err| err.describe(IndexBase::One))
+				.collect::<Vec<_>>(),
+			[
+				"Command 2 (`move 3 from 2 to 3`): Stack 2 only has 2 crates",
+				"Command 4 (`move 1 from 4 to 1`): There is no stack 4"
+			]
+		);
+
+		let mut out = Vec::new();
+		assert!(dry_run(&mut out, lines.iter(), &stacks, IndexBase::One).is_err());
+		assert_eq!(String::from_utf8(out).unwrap().lines().count(), 2);
+		// Nothing was moved
+		assert_eq!(top_crates(&stacks), "NDP");
+	}
+
+	#[test]
+	fn index_base() {
+		let (stacks, _) = parse_input(std::io::Cursor::new(EXAMPLE));
+		// The second command moves 3 crates from stack 1, which only has 2
+		let lines = ["move 1 from 2 to 3", "move 3 from 1 to 3"].map(str::to_string);
 
-		assert_eq!(top, "CMZ");
+		let err = simulate::<true, _>(lines.into_iter(), stacks.clone()).unwrap_err();
+		assert_eq!(
+			err.describe(IndexBase::One),
+			"Command 2 (`move 3 from 1 to 3`): Stack 1 only has 2 crates"
+		);
+		assert_eq!(
+			err.describe(IndexBase::Zero),
+			"Command 2 (`move 3 from 0 to 2`): Stack 0 only has 2 crates"
+		);
 
-		let tops = simulate::<false, _>(lines, stacks).collect::<Vec<_>>();
-		let top = String::from_utf8_lossy(&tops);
+		let err = simulate::<true, _>(std::iter::once("move 1 from 2 to 4".to_string()), stacks)
+			.unwrap_err();
+		assert_eq!(
+			err.describe(IndexBase::One),
+			"Command 1 (`move 1 from 2 to 4`): There is no stack 4"
+		);
 
-		assert_eq!(top, "MCD");
+		assert!("move 1 from 0 to 1".parse::<Command>().is_err());
 	}
 }
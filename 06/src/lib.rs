@@ -0,0 +1,652 @@
+#![deny(clippy::pedantic)]
+use std::{
+	collections::VecDeque,
+	io::{self, BufRead},
+};
+
+/// A marker found in a datastream - a window of characters which satisfies the marker condition
+#[derive(Debug, PartialEq, Eq)]
+pub struct Marker {
+	/// The number of characters which had to be consumed to find the marker,
+	/// which is one past the index of the last character in the window
+	pub end_index: usize,
+	/// The index of the first character in the window
+	pub start_index: usize,
+	/// The characters in the window
+	pub chars: String,
+}
+
+/// Converts a u8 representing one lowercase ascii letter of the alphabet to a single u32,
+/// with a single bit set to 1. There are 26 such characters and 32 available bits, so each one is unique.
+fn convert_bits(c: u8) -> u32 {
+	1 << (c - b'a')
+}
+
+/// Find the number of characters which have to be consumed before each window of `WINDOW_SIZE` unique characters
+/// has been seen, in order. Windows may overlap each other. The datastream is scanned lazily, so taking only the first
+/// few positions doesn't scan the rest of it.
+///
+/// # Panics
+/// While iterating, if any character in the datastream isn't a lowercase ascii letter
+pub fn iter_markers<const WINDOW_SIZE: usize>(string: &str) -> impl Iterator<Item = usize> + '_ {
+	let mut iter = string.as_bytes().iter().map(|c| convert_bits(*c));
+	// A queue for remembering which items are currently being considered in the window.
+	let mut window = VecDeque::with_capacity(WINDOW_SIZE);
+	window.extend(iter.clone().take(WINDOW_SIZE));
+
+	// A checksum value which can be used to keep track of the number of unique items in the window.
+	// We initialize it to be the XOR of all of the items in the first window, or None if the datastream is empty.
+	let checksum = iter.by_ref().take(WINDOW_SIZE).reduce(|acc, c| acc ^ c);
+
+	// Out first checksum was already calculated, so the iterator should start with that one
+	checksum
+		.into_iter()
+		// Then after the first checksum, we calculate progressive checksums by popping out the
+		// last item from the previous window, XORing it with the previous checksum (therefore removing it since X ^ c ^ X = c),
+		// and XORing in the item newly added to the window. If the datastream is empty, there's nothing left to scan.
+		.chain(iter.scan(checksum.unwrap_or(0), move |checksum, c| {
+			let remove = window.pop_front().unwrap();
+			window.push_back(c);
+			*checksum ^= remove ^ c;
+			Some(*checksum)
+		}))
+		// Enumerate so we can find the index of the correct checksums
+		.enumerate()
+		// The correct checksums are the ones with a number of ones set equal to the number of items in the window
+		.filter(|(_, checksum)| checksum.count_ones() == (WINDOW_SIZE.try_into().unwrap()))
+		// We had to consume a window of characters to get the first checksum, so add the window size to the positions
+		.map(|(i, _)| i + WINDOW_SIZE)
+}
+
+/// Find the number of characters which have to be consumed before the first window of `WINDOW_SIZE` unique characters
+/// has been seen, or `None` if there is no such window.
+///
+/// # Panics
+/// If any character in the datastream up to the first marker isn't a lowercase ascii letter
+#[must_use]
+pub fn find_start_of_packet<const WINDOW_SIZE: usize>(string: &str) -> Option<usize> {
+	iter_markers::<WINDOW_SIZE>(string).next()
+}
+
+/// A generalization of [`find_start_of_packet`], where rather than every character in the window needing to be unique,
+/// each character may appear in the window up to `max_repeat` times. A single bit per character can't keep track of
+/// more than one occurrence, so this keeps a count of each character in the window instead.
+#[must_use]
+pub fn find_start_of_packet_max_repeat<const WINDOW_SIZE: usize>(
+	string: &str,
+	max_repeat: usize,
+) -> Option<usize> {
+	max_repeat_end(string.as_bytes(), WINDOW_SIZE, max_repeat)
+}
+
+/// The implementation of [`find_start_of_packet_max_repeat`], with a window size which is only known at runtime
+fn max_repeat_end(bytes: &[u8], window_size: usize, max_repeat: usize) -> Option<usize> {
+	// How many times each character appears in the current window
+	let mut counts = [0usize; 256];
+	// How many characters appear in the current window more than max_repeat times. The window is a marker when this is 0.
+	let mut num_over = 0;
+
+	for (i, c) in bytes.iter().map(|c| usize::from(*c)).enumerate() {
+		// Add the new character to the window
+		counts[c] += 1;
+		// Rather than comparing to max_repeat + 1, which overflows for the largest max_repeat
+		if counts[c] - 1 == max_repeat {
+			num_over += 1;
+		}
+
+		// Once the window is full, the oldest character in it needs to be removed as well
+		if i >= window_size {
+			let remove = usize::from(bytes[i - window_size]);
+			if counts[remove] - 1 == max_repeat {
+				num_over -= 1;
+			}
+			counts[remove] -= 1;
+		}
+
+		// i is the index of the last character in the window, so i + 1 characters have been consumed
+		if i + 1 >= window_size && num_over == 0 {
+			return Some(i + 1);
+		}
+	}
+
+	None
+}
+
+/// The largest window which could ever be a marker when no character may appear in it more than `max_repeat` times.
+/// Any larger window would have to repeat some character more than that. Returns `None` if the size is too large to represent,
+/// in which case a window of any size could be a marker.
+#[must_use]
+pub fn max_window_size(max_repeat: usize) -> Option<usize> {
+	max_repeat.checked_mul(256)
+}
+
+/// The same as [`find_marker`], but with a window size which is only known at runtime.
+///
+/// # Panics
+/// If `window_size` is 0, since an empty window can't be a marker
+#[must_use]
+pub fn find_marker_with_window(
+	string: &str,
+	window_size: usize,
+	max_repeat: usize,
+) -> Option<Marker> {
+	// Windows are counted in bytes, so they may split a multibyte character
+	find_marker_in_bytes(string.as_bytes(), window_size, max_repeat)
+}
+
+/// The same as [`find_marker_with_window`], but the datastream is raw bytes rather than text, so any of the 256 possible bytes
+/// may be part of a marker. The characters of the marker are converted to text lossily.
+///
+/// # Panics
+/// If `window_size` is 0, since an empty window can't be a marker
+#[must_use]
+pub fn find_marker_in_bytes(bytes: &[u8], window_size: usize, max_repeat: usize) -> Option<Marker> {
+	assert!(
+		window_size >= 1,
+		"Marker windows must contain at least one character"
+	);
+
+	let end_index = max_repeat_end(bytes, window_size, max_repeat)?;
+	let start_index = end_index - window_size;
+
+	Some(Marker {
+		end_index,
+		start_index,
+		chars: String::from_utf8_lossy(&bytes[start_index..end_index]).into_owned(),
+	})
+}
+
+/// Find the first marker in a datastream, where no character appears in the window more than `max_repeat` times.
+/// Returns `None` if there is no such marker, such as when the datastream is a single repeated character.
+///
+/// # Panics
+/// If `max_repeat` is 1 and any character in the datastream isn't a lowercase ascii letter
+#[must_use]
+pub fn find_marker<const WINDOW_SIZE: usize>(string: &str, max_repeat: usize) -> Option<Marker> {
+	// The bit-based scan is faster, but can only be used when every character must be unique
+	let end_index = if max_repeat == 1 {
+		find_start_of_packet::<WINDOW_SIZE>(string)
+	} else {
+		find_start_of_packet_max_repeat::<WINDOW_SIZE>(string, max_repeat)
+	}?;
+	let start_index = end_index - WINDOW_SIZE;
+
+	Some(Marker {
+		end_index,
+		start_index,
+		// The window is counted in bytes, so it may split a multibyte character
+		chars: String::from_utf8_lossy(&string.as_bytes()[start_index..end_index]).into_owned(),
+	})
+}
+
+/// Find every marker in a datastream, in order, where no character appears in the window more than `max_repeat` times.
+/// Markers may overlap each other - every window which satisfies the condition is a marker. The datastream is scanned lazily,
+/// so taking only the first few markers doesn't scan the rest of it.
+pub fn find_all_markers<const WINDOW_SIZE: usize>(
+	string: &str,
+	max_repeat: usize,
+) -> impl Iterator<Item = Marker> + '_ {
+	let bytes = string.as_bytes();
+	// Same as find_start_of_packet_max_repeat - the count of each character in the window, and how many are over max_repeat
+	let mut counts = [0usize; 256];
+	let mut num_over = 0;
+
+	bytes.iter().enumerate().filter_map(move |(i, c)| {
+		let c = usize::from(*c);
+		counts[c] += 1;
+		if counts[c] - 1 == max_repeat {
+			num_over += 1;
+		}
+
+		if i >= WINDOW_SIZE {
+			let remove = usize::from(bytes[i - WINDOW_SIZE]);
+			if counts[remove] - 1 == max_repeat {
+				num_over -= 1;
+			}
+			counts[remove] -= 1;
+		}
+
+		(i + 1 >= WINDOW_SIZE && num_over == 0).then(|| Marker {
+			end_index: i + 1,
+			start_index: i + 1 - WINDOW_SIZE,
+			chars: String::from_utf8_lossy(&bytes[i + 1 - WINDOW_SIZE..=i]).into_owned(),
+		})
+	})
+}
+
+/// The same as [`find_all_markers`], but markers don't overlap each other - once a marker is found,
+/// the next marker can only start after the end of it.
+pub fn find_non_overlapping_markers<const WINDOW_SIZE: usize>(
+	string: &str,
+	max_repeat: usize,
+) -> impl Iterator<Item = Marker> + '_ {
+	// The earliest index the next marker may start at
+	let mut next_start = 0;
+
+	find_all_markers::<WINDOW_SIZE>(string, max_repeat).filter(move |marker| {
+		let keep = marker.start_index >= next_start;
+		if keep {
+			next_start = marker.end_index;
+		}
+
+		keep
+	})
+}
+
+/// The same as [`find_marker`], but reads the datastream from `reader` as it goes rather than needing all of it in memory.
+/// Only the current window is kept around, so this works on datastreams of any size. Whitespace at the end of the datastream,
+/// such as the newline at the end of an input file, isn't part of it.
+///
+/// # Errors
+/// If reading from `reader` fails
+pub fn find_marker_in_stream<const WINDOW_SIZE: usize, R: BufRead>(
+	reader: R,
+	max_repeat: usize,
+) -> io::Result<Option<Marker>> {
+	// The current window, as a ring buffer. The character consumed at index i is stored at i % WINDOW_SIZE.
+	let mut window = [0u8; WINDOW_SIZE];
+	// Same as find_start_of_packet_max_repeat - the count of each character in the window, and how many are over max_repeat
+	let mut counts = [0usize; 256];
+	let mut num_over = 0;
+	// The number of characters consumed so far
+	let mut i = 0;
+	// Whitespace which has been read, but might be the end of the datastream rather than part of it
+	let mut held = Vec::new();
+
+	for c in reader.bytes() {
+		let c = c?;
+
+		// Only once something other than whitespace comes after it is the whitespace known to be part of the datastream
+		if c.is_ascii_whitespace() {
+			held.push(c);
+			continue;
+		}
+
+		for c in held.drain(..).chain([c]) {
+			// Once the window is full, the oldest character in it is about to be overwritten, so remove it first
+			if i >= WINDOW_SIZE {
+				let remove = usize::from(window[i % WINDOW_SIZE]);
+				if counts[remove] - 1 == max_repeat {
+					num_over -= 1;
+				}
+				counts[remove] -= 1;
+			}
+
+			window[i % WINDOW_SIZE] = c;
+			counts[usize::from(c)] += 1;
+			if counts[usize::from(c)] - 1 == max_repeat {
+				num_over += 1;
+			}
+
+			if i + 1 >= WINDOW_SIZE && num_over == 0 {
+				// Unroll the ring buffer, starting from the oldest character
+				let (newest, oldest) = window.split_at((i + 1) % WINDOW_SIZE);
+
+				return Ok(Some(Marker {
+					end_index: i + 1,
+					start_index: i + 1 - WINDOW_SIZE,
+					chars: String::from_utf8_lossy(&[oldest, newest].concat()).into_owned(),
+				}));
+			}
+
+			i += 1;
+		}
+	}
+
+	Ok(None)
+}
+
+/// Find the longest run of characters in a datastream where every character is unique, which is the largest window
+/// which could be a marker. If there's more than one longest run, the first one is found. Returns `None` if the datastream is empty.
+#[must_use]
+pub fn longest_distinct_run(string: &str) -> Option<Marker> {
+	let bytes = string.as_bytes();
+	// The index each character was last seen at, if it's been seen yet
+	let mut last_seen = [None; 256];
+	// The start of the current run, and the start and end of the longest run so far
+	let mut start = 0;
+	let mut longest = (0, 0);
+
+	for (i, c) in bytes.iter().enumerate() {
+		// If this character is already in the run, the run has to start after it from now on
+		if let Some(seen) = last_seen[usize::from(*c)] {
+			start = start.max(seen + 1);
+		}
+		last_seen[usize::from(*c)] = Some(i);
+
+		if i + 1 - start > longest.1 - longest.0 {
+			longest = (start, i + 1);
+		}
+	}
+
+	let (start_index, end_index) = longest;
+	(end_index > 0).then(|| Marker {
+		end_index,
+		start_index,
+		chars: String::from_utf8_lossy(&bytes[start_index..end_index]).into_owned(),
+	})
+}
+
+/// Find every distinct character used in a datastream read from `reader`, in ascending order.
+/// Whitespace, such as the newline at the end of an input file, isn't part of the datastream and isn't counted.
+///
+/// # Errors
+/// If reading from `reader` fails
+pub fn alphabet<R: BufRead>(reader: R) -> io::Result<Vec<u8>> {
+	// Same as find_start_of_packet_max_repeat - the count of each character, except over the whole datastream rather than a window
+	let mut counts = [0usize; 256];
+
+	for c in reader.bytes() {
+		counts[usize::from(c?)] += 1;
+	}
+
+	Ok((0..=u8::MAX)
+		.filter(|c| counts[usize::from(*c)] > 0 && !c.is_ascii_whitespace())
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn start_of_packet() {
+		assert_eq!(
+			find_start_of_packet::<4>("bvwbjplbgvbhsrlpgdmjqwftvncz"),
+			Some(5)
+		);
+		assert_eq!(
+			find_start_of_packet::<4>("nppdvjthqldpwncqszvftbrmjlhg"),
+			Some(6)
+		);
+		assert_eq!(
+			find_start_of_packet::<4>("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"),
+			Some(10)
+		);
+		assert_eq!(
+			find_start_of_packet::<4>("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"),
+			Some(11)
+		);
+
+		// Second part
+		assert_eq!(
+			find_start_of_packet::<14>("mjqjpqmgbljsphdztnvjfqwrcgsmlb"),
+			Some(19)
+		);
+		assert_eq!(
+			find_start_of_packet::<14>("bvwbjplbgvbhsrlpgdmjqwftvncz"),
+			Some(23)
+		);
+		assert_eq!(
+			find_start_of_packet::<14>("nppdvjthqldpwncqszvftbrmjlhg"),
+			Some(23)
+		);
+		assert_eq!(
+			find_start_of_packet::<14>("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"),
+			Some(29)
+		);
+		assert_eq!(
+			find_start_of_packet::<14>("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"),
+			Some(26)
+		);
+	}
+
+	#[test]
+	fn marker_positions() {
+		assert_eq!(
+			iter_markers::<4>("abcdddefgh").collect::<Vec<_>>(),
+			[4, 9, 10]
+		);
+		assert_eq!(
+			iter_markers::<4>("abcdefghaaijkl").collect::<Vec<_>>(),
+			find_all_markers::<4>("abcdefghaaijkl", 1)
+				.map(|marker| marker.end_index)
+				.collect::<Vec<_>>()
+		);
+		assert_eq!(iter_markers::<4>("").next(), None);
+		assert_eq!(iter_markers::<4>("abc").next(), None);
+	}
+
+	#[test]
+	fn start_of_packet_max_repeat() {
+		// With no repeats allowed, this should match the bit-based scan
+		for stream in [
+			"mjqjpqmgbljsphdztnvjfqwrcgsmlb",
+			"bvwbjplbgvbhsrlpgdmjqwftvncz",
+			"nppdvjthqldpwncqszvftbrmjlhg",
+			"nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
+			"zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",
+		] {
+			assert_eq!(
+				find_start_of_packet_max_repeat::<4>(stream, 1),
+				find_start_of_packet::<4>(stream)
+			);
+			assert_eq!(
+				find_start_of_packet_max_repeat::<14>(stream, 1),
+				find_start_of_packet::<14>(stream)
+			);
+		}
+
+		// `aaab` has too many `a`s, but `aabb` is fine
+		assert_eq!(find_start_of_packet_max_repeat::<4>("aaabbcc", 2), Some(5));
+		assert_eq!(
+			find_start_of_packet_max_repeat::<4>("aaaaaaabcabc", 2),
+			Some(9)
+		);
+		// Nothing can repeat that many times, so the first window is a marker
+		assert_eq!(
+			find_start_of_packet_max_repeat::<4>("aaaaaaabcabc", usize::MAX),
+			Some(4)
+		);
+	}
+
+	#[test]
+	fn marker() {
+		assert_eq!(
+			find_marker::<4>("bvwbjplbgvbhsrlpgdmjqwftvncz", 1),
+			Some(Marker {
+				end_index: 5,
+				start_index: 1,
+				chars: "vwbj".to_string(),
+			})
+		);
+	}
+
+	#[test]
+	fn marker_multibyte() {
+		// Each é is two bytes, so the first window of 4 bytes ends in the middle of the second one
+		assert_eq!(
+			find_marker::<4>("éaéb", 2),
+			Some(Marker {
+				end_index: 4,
+				start_index: 0,
+				chars: "éa\u{FFFD}".to_string(),
+			})
+		);
+	}
+
+	#[test]
+	fn single_character() {
+		let stream = "a".repeat(100);
+
+		assert_eq!(find_start_of_packet::<4>(&stream), None);
+		assert_eq!(find_start_of_packet_max_repeat::<4>(&stream, 2), None);
+		assert_eq!(find_marker::<4>(&stream, 1), None);
+	}
+
+	#[test]
+	fn stream() {
+		for stream in [
+			"mjqjpqmgbljsphdztnvjfqwrcgsmlb",
+			"bvwbjplbgvbhsrlpgdmjqwftvncz",
+			"nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
+			"aaaaaaabcabc",
+		] {
+			assert_eq!(
+				find_marker_in_stream::<4, _>(stream.as_bytes(), 1).unwrap(),
+				find_marker::<4>(stream, 1)
+			);
+			assert_eq!(
+				find_marker_in_stream::<14, _>(stream.as_bytes(), 1).unwrap(),
+				find_marker::<14>(stream, 1)
+			);
+			assert_eq!(
+				find_marker_in_stream::<4, _>(stream.as_bytes(), 2).unwrap(),
+				find_marker::<4>(stream, 2)
+			);
+		}
+
+		// Whitespace is only part of the datastream when something else comes after it
+		assert_eq!(
+			find_marker_in_stream::<4, _>("abc\n".as_bytes(), 1).unwrap(),
+			None
+		);
+		assert_eq!(
+			find_marker_in_stream::<4, _>("aab c\n".as_bytes(), 1)
+				.unwrap()
+				.unwrap()
+				.chars,
+			"ab c"
+		);
+	}
+
+	#[test]
+	fn all_markers() {
+		let markers = find_all_markers::<4>("abcdddefgh", 1).collect::<Vec<_>>();
+
+		assert_eq!(
+			markers
+				.iter()
+				.map(|marker| marker.end_index)
+				.collect::<Vec<_>>(),
+			[4, 9, 10]
+		);
+		assert_eq!(
+			find_all_markers::<4>("abcdddefgh", 1).nth(1),
+			Some(Marker {
+				end_index: 9,
+				start_index: 5,
+				chars: "defg".to_string(),
+			})
+		);
+		assert_eq!(
+			find_all_markers::<4>("bvwbjplbgvbhsrlpgdmjqwftvncz", 1).next(),
+			find_marker::<4>("bvwbjplbgvbhsrlpgdmjqwftvncz", 1)
+		);
+	}
+
+	#[test]
+	fn non_overlapping_markers() {
+		let stream = "abcdefghaaijkl";
+		let end_indices = |markers: &mut dyn Iterator<Item = Marker>| {
+			markers.map(|marker| marker.end_index).collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			end_indices(&mut find_all_markers::<4>(stream, 1)),
+			[4, 5, 6, 7, 8, 9, 13, 14]
+		);
+		assert_eq!(
+			end_indices(&mut find_non_overlapping_markers::<4>(stream, 1)),
+			[4, 8, 13]
+		);
+	}
+
+	#[test]
+	fn test_alphabet() {
+		let alphabet = alphabet("mjqjpqmgbljsphdztnvjfqwrcgsmlb\n".as_bytes()).unwrap();
+
+		assert_eq!(alphabet.len(), 18);
+		assert_eq!(alphabet, b"bcdfghjlmnpqrstvwz");
+	}
+
+	#[test]
+	fn runtime_window() {
+		for stream in [
+			"mjqjpqmgbljsphdztnvjfqwrcgsmlb",
+			"bvwbjplbgvbhsrlpgdmjqwftvncz",
+			"nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
+		] {
+			assert_eq!(
+				find_marker_with_window(stream, 4, 1),
+				find_marker::<4>(stream, 1)
+			);
+			assert_eq!(
+				find_marker_with_window(stream, 14, 1),
+				find_marker::<14>(stream, 1)
+			);
+		}
+
+		// Any single character is a marker on its own, so the first one is always the marker
+		assert_eq!(
+			find_marker_with_window("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 1, 1),
+			Some(Marker {
+				end_index: 1,
+				start_index: 0,
+				chars: "m".to_string(),
+			})
+		);
+	}
+
+	#[test]
+	#[should_panic]
+	fn empty_window() {
+		let _ = find_marker_with_window("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 0, 1);
+	}
+
+	#[test]
+	fn runtime_window_multibyte() {
+		// Each é is two bytes, so these windows end in the middle of one
+		assert_eq!(
+			find_marker_with_window("éaéb", 1, 1),
+			Some(Marker {
+				end_index: 1,
+				start_index: 0,
+				chars: "\u{FFFD}".to_string(),
+			})
+		);
+		assert_eq!(
+			find_marker_with_window("abé", 3, 1).map(|marker| marker.chars),
+			Some("ab\u{FFFD}".to_string())
+		);
+	}
+
+	#[test]
+	fn longest_run() {
+		let run = longest_distinct_run("abcabcdbb").unwrap();
+		assert_eq!((run.start_index, run.end_index), (3, 7));
+		assert_eq!(run.chars, "abcd");
+
+		// The first of the two longest runs
+		assert_eq!(
+			longest_distinct_run("pwwkew").map(|run| run.chars),
+			Some("wke".to_string())
+		);
+		assert_eq!(
+			longest_distinct_run("aaaa").map(|run| run.start_index),
+			Some(0)
+		);
+		// The start-of-message marker of the example is part of a longer run, which goes on to the end
+		assert_eq!(
+			longest_distinct_run("mjqjpqmgbljsphdztnvjfqwrcgsmlb").map(|run| run.chars),
+			Some("phdztnvjfqwrcgsmlb".to_string())
+		);
+		assert_eq!(longest_distinct_run(""), None);
+	}
+
+	#[test]
+	fn binary() {
+		// 0xff and 0xfe can never appear in UTF-8, and the first 4 bytes repeat 0xff
+		let bytes = [0xff, 0x00, 0xff, 0xfe, 0x80, 0x00, 0x01, 0x01];
+		assert!(std::str::from_utf8(&bytes).is_err());
+
+		let marker = find_marker_in_bytes(&bytes, 4, 1).unwrap();
+		assert_eq!((marker.start_index, marker.end_index), (1, 5));
+		assert_eq!(find_marker_in_bytes(&bytes, 6, 1), None);
+		// 0xff may appear twice
+		assert_eq!(
+			find_marker_in_bytes(&bytes, 6, 2).map(|marker| marker.end_index),
+			Some(6)
+		);
+	}
+}
@@ -1,9 +1,22 @@
 #![feature(iter_array_chunks)]
 #![deny(clippy::pedantic)]
-use std::{collections::VecDeque, path::PathBuf};
+use std::{
+	fs::File,
+	io::{self, IsTerminal, Read},
+	num::NonZeroUsize,
+	path::PathBuf,
+	time::{Duration, Instant},
+};
 
-use anyhow::Result;
-use clap::{Parser, ValueEnum};
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use signal_finder::{
+	alphabet, find_all_markers, find_marker, find_marker_in_bytes, find_marker_in_stream,
+	find_marker_with_window, find_non_overlapping_markers, find_start_of_packet,
+	longest_distinct_run, max_window_size, Marker,
+};
 
 #[derive(Clone, ValueEnum)]
 enum Mode {
@@ -14,7 +27,8 @@ enum Mode {
 }
 
 impl Mode {
-	const fn window_size(&self) -> usize {
+	/// The number of characters in the marker window of this mode
+	fn window_size(&self) -> usize {
 		match self {
 			Mode::Packet => 4,
 			Mode::Message => 14,
@@ -23,72 +37,363 @@ impl Mode {
 }
 
 #[derive(Parser)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
+	#[command(subcommand)]
+	command: Option<Command>,
 	/// Input file path
 	#[arg(short, long, default_value = "input.txt")]
 	input_file: PathBuf,
 	/// What mode to run the program in
-	#[arg(value_enum)]
-	mode: Mode,
-}
-
-/// Converts a u8 representing one lowercase ascii letter of the alphabet to a single u32,
-/// with a single bit set to 1. There are 26 such characters and 32 available bits, so each one is unique.
-fn convert_bits(c: u8) -> u32 {
-	1 << (c - b'a')
-}
-
-fn find_start_of_packet<const WINDOW_SIZE: usize>(string: &str) -> usize {
-	let mut iter = string.as_bytes().iter().map(|c| convert_bits(*c));
-	// A queue for remembering which items are currently being considered in the window.
-	let mut window = VecDeque::with_capacity(WINDOW_SIZE);
-	window.extend(iter.clone().take(WINDOW_SIZE));
-
-	// A checksum value which can be used to keep track of the number of unique items in the window.
-	// We initialize it to be the XOR of all of the items in the first window
-	let checksum = iter
-		.by_ref()
-		.take(WINDOW_SIZE)
-		.reduce(|acc, c| acc ^ c)
-		.unwrap();
-
-	// Out first checksum was already calculated, so the iterator should start with that one
-	let (i, _) = std::iter::once(checksum)
-		// Then after the first checksum, we calculate progressive checksums by popping out the
-		// last item from the previous window, XORing it with the previous checksum (therefore removing it since X ^ c ^ X = c),
-		// and XORing in the item newly added to the window.
-		.chain(iter.scan(checksum, |checksum, c| {
-			let remove = window.pop_front().unwrap();
-			window.push_back(c);
-			*checksum ^= remove ^ c;
-			Some(*checksum)
-		}))
-		// Enumerate so we can find the index of the correct checksum
-		.enumerate()
-		// The correct checksum is the one with a number of ones set equal to the number of items in the window
-		.find(|(_, checksum)| checksum.count_ones() == (WINDOW_SIZE.try_into().unwrap()))
-		.unwrap();
-
-	// We had to consume a window of characters to get the first checksum, so add the window size to the return value
-	i + WINDOW_SIZE
+	#[arg(value_enum, required_unless_present_any = ["analyze", "longest", "window"])]
+	mode: Option<Mode>,
+	/// Instead of using the window size of a mode, look for a marker which is a window of this many characters
+	#[arg(long, value_name = "N", conflicts_with_all = ["stream", "nth", "all"])]
+	window: Option<usize>,
+	/// The maximum number of times any one character may appear in the marker window.
+	/// 1 means every character must be unique, as in the original problem
+	#[arg(long, default_value_t = 1, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+	max_repeat: usize,
+	/// Scan the input file as it's read rather than reading all of it into memory first, for very large datastreams.
+	/// Shows a progress bar for large files when running in a terminal
+	#[arg(long)]
+	stream: bool,
+	/// Read the input file as raw bytes rather than text, so that any file can be scanned for a marker made of any bytes
+	#[arg(long, conflicts_with_all = ["stream", "nth", "all", "analyze"])]
+	binary: bool,
+	/// Treat each line of the input file as its own datastream, and find the first marker in each one
+	#[arg(long, conflicts_with_all = ["stream", "binary", "nth", "all", "analyze", "longest"])]
+	per_line: bool,
+	#[command(flatten)]
+	enumeration: Enumeration,
+	#[command(flatten)]
+	survey: Survey,
+}
+
+#[derive(clap::Args)]
+struct Survey {
+	/// Instead of finding a marker, scan the whole datastream and report how many distinct characters it uses, and which ones
+	#[arg(long, conflicts_with_all = ["nth", "all"])]
+	analyze: bool,
+	/// Instead of finding a marker, find the longest run of unique characters in the datastream, and where it is
+	#[arg(long, conflicts_with_all = ["analyze", "window", "stream", "binary", "nth", "all"])]
+	longest: bool,
+}
+
+#[derive(clap::Args)]
+struct Enumeration {
+	/// Find the Nth marker in the datastream rather than the first. Markers may overlap unless `--non-overlapping` is given
+	#[arg(long, value_name = "N", conflicts_with = "stream")]
+	nth: Option<NonZeroUsize>,
+	/// Find every marker in the datastream rather than the first. Markers may overlap unless `--non-overlapping` is given
+	#[arg(long, conflicts_with_all = ["stream", "nth"])]
+	all: bool,
+	/// When finding more than one marker, only start looking for the next marker after the end of the last one
+	#[arg(long)]
+	non_overlapping: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Instead of reading a datastream, generate a random one and time how long it takes to find the first marker
+	Bench {
+		/// Which marker to look for
+		#[arg(short, long, value_enum, default_value_t = Mode::Message)]
+		mode: Mode,
+		/// The number of characters in the datastream
+		#[arg(short, long)]
+		length: usize,
+		/// The number of different letters in the datastream, starting from `a`
+		#[arg(short, long, default_value_t = 26, value_parser = clap::value_parser!(u8).range(1..=26))]
+		alphabet: u8,
+		/// Seed for the random number generator, so that the same datastream can be generated again
+		#[arg(short, long, default_value_t = 0)]
+		seed: u64,
+	},
+}
+
+/// Files smaller than this are scanned quickly enough not to need a progress bar
+const PROGRESS_BAR_MIN_BYTES: u64 = 64 * 1024 * 1024;
+
+/// A progress bar for scanning `len` bytes of a datastream. Hidden if the datastream is small or nobody is watching.
+fn progress_bar(len: u64) -> ProgressBar {
+	if len < PROGRESS_BAR_MIN_BYTES || !io::stderr().is_terminal() {
+		return ProgressBar::hidden();
+	}
+
+	let pb = ProgressBar::new(len).with_style(
+		ProgressStyle::with_template(
+			"{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+		)
+		.unwrap()
+		.progress_chars("#>-"),
+	);
+	// Don't redraw every time more of the file is read. Instead do it every .1 second.
+	pb.enable_steady_tick(Duration::from_millis(100));
+
+	pb
+}
+
+/// Scan a datastream from `reader` for a marker, counting every byte read on `pb`
+fn scan_stream<const WINDOW_SIZE: usize, R: io::Read>(
+	reader: R,
+	max_repeat: usize,
+	pb: &ProgressBar,
+) -> io::Result<Option<signal_finder::Marker>> {
+	find_marker_in_stream::<WINDOW_SIZE, _>(io::BufReader::new(pb.wrap_read(reader)), max_repeat)
+}
+
+/// Read the whole datastream from `reader`. The newline at the end of an input file isn't part of the datastream, so it's left out.
+fn read_datastream<R: Read>(mut reader: R) -> io::Result<String> {
+	let mut communication = String::new();
+	reader.read_to_string(&mut communication)?;
+	communication.truncate(communication.trim_end().len());
+
+	Ok(communication)
+}
+
+/// Every marker in a datastream, either with overlapping markers or without
+fn enumerate_markers<const WINDOW_SIZE: usize>(
+	string: &str,
+	max_repeat: usize,
+	non_overlapping: bool,
+) -> Box<dyn Iterator<Item = Marker> + '_> {
+	if non_overlapping {
+		Box::new(find_non_overlapping_markers::<WINDOW_SIZE>(
+			string, max_repeat,
+		))
+	} else {
+		Box::new(find_all_markers::<WINDOW_SIZE>(string, max_repeat))
+	}
+}
+
+/// Generate a random datastream of `length` characters, each drawn uniformly from the first `alphabet` lowercase letters
+fn generate_stream<R: Rng>(rng: &mut R, length: usize, alphabet: u8) -> String {
+	(0..length)
+		.map(|_| char::from(b'a' + rng.gen_range(0..alphabet)))
+		.collect()
+}
+
+/// Generate a random datastream as in [`generate_stream`], and print where its first marker is and how long it took to find
+fn bench(mode: &Mode, length: usize, alphabet: u8, seed: u64) {
+	let stream = generate_stream(&mut StdRng::seed_from_u64(seed), length, alphabet);
+
+	// Only time finding the marker, not generating the datastream
+	let start = Instant::now();
+	let marker = match mode {
+		Mode::Packet => find_start_of_packet::<4>(&stream),
+		Mode::Message => find_start_of_packet::<14>(&stream),
+	};
+	let elapsed = start.elapsed();
+
+	match marker {
+		Some(end_index) => println!("Marker: {end_index}"),
+		None => println!("No marker found in the datastream"),
+	}
+	println!("Time: {elapsed:?}");
+}
+
+/// Print every marker along with the number of characters consumed to find it, followed by how many markers there were
+fn print_all_markers(markers: impl Iterator<Item = Marker>) {
+	let mut count = 0;
+	for marker in markers {
+		println!("{} {}", marker.end_index, marker.chars);
+		count += 1;
+	}
+	println!("Markers: {count}");
+}
+
+/// Print the characters of a marker and the number of characters consumed to find it, or that there wasn't one
+fn print_marker(marker: Option<Marker>) {
+	match marker {
+		Some(marker) => {
+			println!("{}", marker.chars);
+			println!("{}", marker.end_index);
+		}
+		None => println!("No marker found in the datastream"),
+	}
+}
+
+/// Print the characters of the longest run of unique characters, how long it is, and where it starts, or that there wasn't one
+fn print_longest(run: Option<Marker>) {
+	match run {
+		Some(run) => {
+			println!("{}", run.chars);
+			println!("Length: {}", run.end_index - run.start_index);
+			println!("Start: {}", run.start_index);
+		}
+		None => println!("The datastream is empty"),
+	}
+}
+
+/// Find the first marker in each line of `communication` as if each line were its own datastream, with windows of `window_size`
+/// characters where no character appears more than `max_repeat` times
+fn per_line_markers(
+	communication: &str,
+	window_size: usize,
+	max_repeat: usize,
+) -> Vec<Option<Marker>> {
+	communication
+		.lines()
+		.map(|line| find_marker_with_window(line, window_size, max_repeat))
+		.collect()
+}
+
+/// Print the number of characters consumed to find the marker in each line, as found by [`per_line_markers`],
+/// or that there wasn't one
+fn print_per_line(markers: &[Option<Marker>]) {
+	for (i, marker) in markers.iter().enumerate() {
+		match marker {
+			Some(marker) => println!("Line {}: {}", i + 1, marker.end_index),
+			None => println!("Line {}: No marker found", i + 1),
+		}
+	}
+}
+
+/// Make sure that a window of `window_size` characters could be a marker, where no character appears more than `max_repeat` times
+fn check_window(window_size: usize, max_repeat: usize) -> Result<()> {
+	if window_size == 0 {
+		return Err(anyhow!("The window must contain at least one character"));
+	}
+
+	if let Some(max) = max_window_size(max_repeat) {
+		if window_size > max {
+			return Err(anyhow!(
+				"A window of {window_size} characters can't be a marker with at most {max_repeat} of each character, the largest possible is {max}"
+			));
+		}
+	}
+
+	Ok(())
 }
 
 fn main() -> Result<()> {
 	let args = Args::parse();
 
-	let communication = std::fs::read_to_string(args.input_file)?;
-	let packet_start = match args.mode {
-		Mode::Packet => find_start_of_packet::<4>(&communication),
-		Mode::Message => find_start_of_packet::<14>(&communication),
+	if let Some(Command::Bench {
+		mode,
+		length,
+		alphabet,
+		seed,
+	}) = args.command
+	{
+		bench(&mode, length, alphabet, seed);
+		return Ok(());
+	}
+
+	if args.survey.analyze {
+		let file = File::open(args.input_file)?;
+		let pb = progress_bar(file.metadata()?.len());
+
+		let alphabet = alphabet(io::BufReader::new(pb.wrap_read(file)))?;
+		pb.finish_and_clear();
+
+		println!("Distinct characters: {}", alphabet.len());
+		println!("Characters: {}", String::from_utf8_lossy(&alphabet));
+
+		return Ok(());
+	}
+
+	if args.survey.longest {
+		let communication = read_datastream(File::open(args.input_file)?)?;
+
+		print_longest(longest_distinct_run(&communication));
+
+		return Ok(());
+	}
+
+	// Mode is required unless using a custom window
+	let window = match (args.window, &args.mode) {
+		(Some(window), _) => window,
+		(None, mode) => mode.as_ref().unwrap().window_size(),
 	};
 
-	// packet_start is the number of characters which had to be consumed to find the packet start.
-	// This means it is the index of the last character in the window
-	println!(
-		"{}",
-		&communication[(packet_start - args.mode.window_size())..packet_start]
-	);
-	println!("{packet_start}");
+	if args.binary {
+		let bytes = std::fs::read(args.input_file)?;
+		check_window(window, args.max_repeat)?;
+
+		print_marker(find_marker_in_bytes(&bytes, window, args.max_repeat));
+
+		return Ok(());
+	}
+
+	if args.per_line {
+		let communication = std::fs::read_to_string(args.input_file)?;
+		check_window(window, args.max_repeat)?;
+
+		print_per_line(&per_line_markers(&communication, window, args.max_repeat));
+
+		return Ok(());
+	}
+
+	if let Some(window) = args.window {
+		check_window(window, args.max_repeat)?;
+		let communication = read_datastream(File::open(args.input_file)?)?;
+
+		print_marker(find_marker_with_window(
+			&communication,
+			window,
+			args.max_repeat,
+		));
+
+		return Ok(());
+	}
+
+	// Mode is required unless benchmarking, analyzing, finding the longest run or using a custom window, which have already returned
+	let mode = args.mode.unwrap();
+	let Enumeration {
+		nth,
+		all,
+		non_overlapping,
+	} = args.enumeration;
+
+	if all {
+		let communication = read_datastream(File::open(args.input_file)?)?;
+		let markers = match mode {
+			Mode::Packet => {
+				enumerate_markers::<4>(&communication, args.max_repeat, non_overlapping)
+			}
+			Mode::Message => {
+				enumerate_markers::<14>(&communication, args.max_repeat, non_overlapping)
+			}
+		};
+
+		print_all_markers(markers);
+
+		return Ok(());
+	}
+
+	let marker = if args.stream {
+		let file = File::open(args.input_file)?;
+		let pb = progress_bar(file.metadata()?.len());
+
+		let marker = match mode {
+			Mode::Packet => scan_stream::<4, _>(file, args.max_repeat, &pb),
+			Mode::Message => scan_stream::<14, _>(file, args.max_repeat, &pb),
+		}?;
+		pb.finish_and_clear();
+
+		marker
+	} else {
+		let communication = read_datastream(File::open(args.input_file)?)?;
+
+		match (mode, nth) {
+			(Mode::Packet, None) => find_marker::<4>(&communication, args.max_repeat),
+			(Mode::Message, None) => find_marker::<14>(&communication, args.max_repeat),
+			(Mode::Packet, Some(n)) => Some(
+				enumerate_markers::<4>(&communication, args.max_repeat, non_overlapping)
+					.nth(n.get() - 1)
+					.ok_or_else(|| anyhow!("There are fewer than {n} markers in the datastream"))?,
+			),
+			(Mode::Message, Some(n)) => Some(
+				enumerate_markers::<14>(&communication, args.max_repeat, non_overlapping)
+					.nth(n.get() - 1)
+					.ok_or_else(|| anyhow!("There are fewer than {n} markers in the datastream"))?,
+			),
+		}
+	};
+
+	print_marker(marker);
 
 	Ok(())
 }
@@ -98,38 +403,108 @@ mod tests {
 	use super::*;
 
 	#[test]
-	fn start_of_packet() {
-		assert_eq!(find_start_of_packet::<4>("bvwbjplbgvbhsrlpgdmjqwftvncz"), 5);
-		assert_eq!(find_start_of_packet::<4>("nppdvjthqldpwncqszvftbrmjlhg"), 6);
-		assert_eq!(
-			find_start_of_packet::<4>("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"),
-			10
-		);
-		assert_eq!(
-			find_start_of_packet::<4>("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"),
-			11
-		);
+	fn progress() {
+		// There's no marker, so the whole stream has to be read
+		let stream = "ab".repeat(100_000);
+		let pb = ProgressBar::hidden();
 
-		// Second part
-		assert_eq!(
-			find_start_of_packet::<14>("mjqjpqmgbljsphdztnvjfqwrcgsmlb"),
-			19
-		);
 		assert_eq!(
-			find_start_of_packet::<14>("bvwbjplbgvbhsrlpgdmjqwftvncz"),
-			23
+			scan_stream::<4, _>(stream.as_bytes(), 1, &pb).unwrap(),
+			None
 		);
+		assert_eq!(pb.position(), 200_000);
+	}
+
+	#[test]
+	fn generated_stream() {
+		let stream = generate_stream(&mut StdRng::seed_from_u64(1234), 100_000, 16);
+		assert_eq!(stream.len(), 100_000);
+		assert!(stream.bytes().all(|c| (b'a'..b'q').contains(&c)));
 		assert_eq!(
-			find_start_of_packet::<14>("nppdvjthqldpwncqszvftbrmjlhg"),
-			23
+			stream,
+			generate_stream(&mut StdRng::seed_from_u64(1234), 100_000, 16)
 		);
+
+		assert_eq!(find_start_of_packet::<4>(&stream), Some(4));
+		assert_eq!(find_start_of_packet::<14>(&stream), Some(4708));
+		// Only 13 different letters can't make a window of 14 unique letters
+		let stream = generate_stream(&mut StdRng::seed_from_u64(1234), 100_000, 13);
+		assert_eq!(find_start_of_packet::<14>(&stream), None);
+	}
+
+	#[test]
+	fn trailing_newline() {
+		// Without a marker, the scan reaches the end of the file, and the newline there isn't a lowercase letter
+		let communication = read_datastream("aaaaaaaaaaaaaaaaaaaa\n".as_bytes()).unwrap();
+		assert_eq!(communication, "aaaaaaaaaaaaaaaaaaaa");
+		assert_eq!(find_marker::<4>(&communication, 1), None);
+		assert_eq!(find_marker::<14>(&communication, 1), None);
+
+		let communication =
+			read_datastream("mjqjpqmgbljsphdztnvjfqwrcgsmlb\r\n".as_bytes()).unwrap();
+		assert_eq!(find_marker::<14>(&communication, 1).unwrap().end_index, 19);
+	}
+
+	#[test]
+	fn all_markers_trailing_newline() {
+		// The newline would otherwise make `abc\n` a start-of-packet marker at the end
+		let communication = read_datastream("aabbccabc\n".as_bytes()).unwrap();
+		assert_eq!(enumerate_markers::<4>(&communication, 1, false).count(), 0);
+
+		let markers = enumerate_markers::<3>(&communication, 1, false)
+			.map(|marker| (marker.end_index, marker.chars))
+			.collect::<Vec<_>>();
+		assert_eq!(markers, [(8, "cab".to_owned()), (9, "abc".to_owned())]);
+
+		let pb = ProgressBar::hidden();
 		assert_eq!(
-			find_start_of_packet::<14>("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg"),
-			29
+			scan_stream::<4, _>("aaaaaaaaaaaaaaaaaaaa\n".as_bytes(), 1, &pb).unwrap(),
+			None
 		);
+	}
+
+	#[test]
+	fn per_line() {
+		// The examples from the problem, one per line, along with a line without a marker and a blank line
+		let communication = "mjqjpqmgbljsphdztnvjfqwrcgsmlb
+bvwbjplbgvbhsrlpgdmjqwftvncz
+aaaaaaa
+
+nppdvjthqldpwncqszvftbrmjlhg
+";
+
+		let markers = per_line_markers(communication, 4, 1)
+			.iter()
+			.map(|marker| marker.as_ref().map(|marker| marker.end_index))
+			.collect::<Vec<_>>();
+		assert_eq!(markers, [Some(7), Some(5), None, None, Some(6)]);
+
+		let markers = per_line_markers(communication, 14, 1)
+			.iter()
+			.map(|marker| marker.as_ref().map(|marker| marker.end_index))
+			.collect::<Vec<_>>();
+		assert_eq!(markers, [Some(19), Some(23), None, None, Some(23)]);
+	}
+
+	#[test]
+	fn window() {
+		assert!(check_window(1, 1).is_ok());
+		assert!(check_window(14, 1).is_ok());
+		assert!(check_window(0, 1).is_err());
+		assert!(check_window(257, 1).is_err());
+		assert!(check_window(257, 2).is_ok());
+		// 256 times this many repeats can't be represented, so there's no limit on the window
+		assert!(check_window(usize::MAX, usize::MAX / 2).is_ok());
+	}
+
+	#[test]
+	fn max_repeat() {
+		assert!(Args::try_parse_from(["signal-finder", "packet", "--max-repeat", "0"]).is_err());
 		assert_eq!(
-			find_start_of_packet::<14>("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw"),
-			26
+			Args::try_parse_from(["signal-finder", "packet", "--max-repeat", "2"])
+				.unwrap()
+				.max_repeat,
+			2
 		);
 	}
 }
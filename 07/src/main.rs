@@ -1,5 +1,7 @@
 #![deny(clippy::pedantic)]
 use std::{
+	collections::BTreeMap,
+	fmt::Write,
 	fs::File,
 	io::{self, BufRead},
 	path::PathBuf,
@@ -7,9 +9,10 @@ use std::{
 };
 
 use anyhow::{anyhow, Result};
-use clap::{Parser, ValueEnum};
+use clap::{ArgGroup, Parser, ValueEnum};
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, ValueEnum)]
 enum Mode {
@@ -17,28 +20,73 @@ enum Mode {
 	SmallDirSize,
 	/// The second variant of the problem, where we find the size of the smallest directory we can delete which will give us enough free space
 	FreeSpace,
+	/// Count the directories which are at least as large as a threshold
+	CountLarge,
+	/// Find the total size of all directories which don't have any directories inside of them
+	LeafDirSize,
+}
+
+#[derive(Clone, ValueEnum)]
+enum Format {
+	/// An indented tree, with one directory or file per line
+	Plain,
+	/// A tree of nodes with a name, size, and children, for use with other programs
+	Json,
 }
 
 #[derive(Parser)]
+// Only one thing can be done at once, whether that's running a mode or querying the filesystem tree.
+// Both ends of a range of sizes can be given together, so they aren't part of the group, but conflict with it.
+#[command(group(ArgGroup::new("query").args(["mode", "tree", "path", "du", "between"]).multiple(false)))]
 struct Args {
 	/// Input file path
 	#[arg(short, long, default_value = "input.txt")]
 	input_file: PathBuf,
 	/// What mode to run the program in
-	#[arg(value_enum)]
-	mode: Mode,
+	#[arg(value_enum, required_unless_present_any = ["tree", "path", "min_size", "max_size", "du", "between"])]
+	mode: Option<Mode>,
+	/// Instead of running a mode, print a tree view of the filesystem with the sizes of every directory and file
+	#[arg(long)]
+	tree: bool,
+	/// How to print the tree view of the filesystem
+	#[arg(long, value_enum, default_value_t = Format::Plain, requires = "tree")]
+	format: Format,
+	/// Instead of running a mode, print the size of the directory at this path (such as `/a/e`)
+	#[arg(long)]
+	path: Option<String>,
+	/// Instead of running a mode, list the path and size of every directory at least this large
+	#[arg(long, conflicts_with = "query")]
+	min_size: Option<u64>,
+	/// Instead of running a mode, list the path and size of every directory at most this large
+	#[arg(long, conflicts_with = "query")]
+	max_size: Option<u64>,
+	/// Instead of running a mode, sum the sizes of every directory whose size is between MIN and MAX (inclusive)
+	#[arg(long, num_args = 2, value_names = ["MIN", "MAX"])]
+	between: Option<Vec<u64>>,
+	/// Instead of running a mode, list the size and path of every directory like `du -b` does, sorted by path
+	#[arg(long)]
+	du: bool,
+	/// The smallest size of directory to count, when counting large directories
+	#[arg(long, default_value_t = 100_000)]
+	threshold: u64,
+	/// Fail if the session lists files before changing into a directory, instead of treating them as being in the root directory
+	#[arg(long)]
+	strict: bool,
+	/// Only report, count, or sum directories at most this many levels below the root directory, which is at level 0
+	#[arg(long, value_name = "D", conflicts_with_all = ["tree", "path"])]
+	max_depth: Option<usize>,
 }
 
 /// An enum which keeps track of listings that actually matter:
 /// - `cd ..`   (traversing to a parent directory)
 /// - `cd a`    (traversing to a child directory (here named 'a'))
-/// - `29116 f` (the size of a file in a directory)
+/// - `29116 f` (the size of a file (here named 'f') in a directory)
 ///
 /// ls and dir listings not represented because they don't provide any meaningful information
 enum Listing {
-	ChangeDirDown,
+	ChangeDirDown(String),
 	ChangeDirUp,
-	File(u64),
+	File(String, u64),
 }
 
 impl FromStr for Listing {
@@ -51,7 +99,7 @@ impl FromStr for Listing {
 		// the variant after matching a single time.
 		lazy_static! {
 			static ref REGEX: Regex = Regex::new(
-				r"^(?:\$ cd (?:(?P<dir_up>\.\.)|(?P<dir_down>\S+)))|(?:(?P<file_size>\d+) \S+)$"
+				r"^(?:\$ cd (?:(?P<dir_up>\.\.)|(?P<dir_down>\S+))|(?P<file_size>\d+) (?P<file_name>\S+))$"
 			)
 			.unwrap();
 		}
@@ -60,10 +108,15 @@ impl FromStr for Listing {
 			Some(captures) => {
 				if captures.name("dir_up").is_some() {
 					Ok(Listing::ChangeDirUp)
-				} else if captures.name("dir_down").is_some() {
-					Ok(Listing::ChangeDirDown)
-				} else if let Some(size) = captures.name("file_size") {
-					Ok(Listing::File(size.as_str().parse()?))
+				} else if let Some(name) = captures.name("dir_down") {
+					Ok(Listing::ChangeDirDown(name.as_str().to_string()))
+				} else if let (Some(size), Some(name)) =
+					(captures.name("file_size"), captures.name("file_name"))
+				{
+					Ok(Listing::File(
+						name.as_str().to_string(),
+						size.as_str().parse()?,
+					))
 				}
 				// If we matched, we should have matched one of those capture groups
 				else {
@@ -78,68 +131,157 @@ impl FromStr for Listing {
 	}
 }
 
-/// Finds the total size of all directories below a certain max size (100,000)
+impl Listing {
+	/// Split a change of directory through multiple directories at once (such as `cd a/b` or `cd ../c`)
+	/// into one change of directory per component of the path. An absolute path (such as `cd /a`) starts by changing into `/`.
+	/// `.` doesn't go anywhere, so `cd .` doesn't change directory at all.
+	fn split_path(self) -> Vec<Listing> {
+		match self {
+			Listing::ChangeDirDown(path) if path == "." => Vec::new(),
+			Listing::ChangeDirDown(path) if path != "/" && path.contains('/') => path
+				.starts_with('/')
+				.then(|| Listing::ChangeDirDown("/".to_string()))
+				.into_iter()
+				.chain(
+					path.split('/')
+						// Ignore doubled or trailing `/`s, as well as `.` which doesn't go anywhere
+						.filter(|name| !name.is_empty() && *name != ".")
+						.map(|name| {
+							if name == ".." {
+								Listing::ChangeDirUp
+							} else {
+								Listing::ChangeDirDown(name.to_string())
+							}
+						}),
+				)
+				.collect(),
+			listing => vec![listing],
+		}
+	}
+}
+
+/// Parse the meaningful listings out of the lines of a terminal session,
+/// with one change of directory per directory traversed.
+/// If the session lists files before changing into any directory, they're in the root directory, so it starts with `cd /`.
+fn listings<T: Iterator<Item = String>>(lines: T) -> impl Iterator<Item = Listing> {
+	let mut listings = lines
+		.flat_map(|line| line.parse::<Listing>())
+		.flat_map(Listing::split_path)
+		.peekable();
+
+	let implicit_root = match listings.peek() {
+		Some(Listing::ChangeDirDown(_)) | None => None,
+		Some(_) => Some(Listing::ChangeDirDown("/".to_string())),
+	};
+
+	implicit_root.into_iter().chain(listings)
+}
+
+/// Make sure a terminal session changes into a directory before listing any files, rather than relying on [`listings`]
+/// to put them in the root directory
+fn check_first_cd(lines: &[String]) -> Result<()> {
+	match lines
+		.iter()
+		.enumerate()
+		.find_map(|(i, line)| line.parse::<Listing>().ok().map(|listing| (i, listing)))
+	{
+		Some((i, Listing::File(name, _))) => Err(anyhow!(
+			"Line {}: Listed file `{name}` before changing into a directory",
+			i + 1
+		)),
+		Some((i, Listing::ChangeDirUp)) => Err(anyhow!(
+			"Line {}: Tried to go up a directory before changing into one",
+			i + 1
+		)),
+		Some((_, Listing::ChangeDirDown(_))) | None => Ok(()),
+	}
+}
+
+/// Leave the current directory of a traversal, adding its size to the directory above it.
+/// `dir_sizes` is the sizes of the current directory and all of its parent directories, in reverse order.
+/// Returns the size of the directory which was left, or `None` if it's the root directory, which can't be left
+/// (as in a shell, `cd ..` from the root directory stays there).
+fn leave_dir(dir_sizes: &mut Vec<u64>) -> Option<u64> {
+	if dir_sizes.len() < 2 {
+		return None;
+	}
+
+	let size = dir_sizes.pop().unwrap();
+
+	// Each directory above this one also has the size of this directory
+	*dir_sizes.last_mut().unwrap() += size;
+
+	Some(size)
+}
+
+/// Finds the total size of all directories below a certain max size (100,000), and at most `max_depth` levels below the root directory,
 /// from a list of commands navigating directories.
-fn total_size<T: Iterator<Item = String>>(lines: T) -> u64 {
+fn total_size<T: Iterator<Item = String>>(lines: T, max_depth: usize) -> u64 {
 	const MAX_SIZE: u64 = 100_000;
 	// The total which we will return later
 	let mut sum = 0;
 
-	// A list of sizes of a directory and all of its parent directories, in reverse order
+	// A list of sizes of a directory and all of its parent directories, in reverse order.
+	// The root directory is at the bottom, so each directory's depth is its index in the list.
 	let mut dir_sizes = Vec::new();
 
-	lines
-		// Parse each line
-		.flat_map(|line| line.parse::<Listing>())
-		.for_each(|listing| match listing {
-			// If we're going down in directories (such as with `cd a`), add a new empty directory
-			Listing::ChangeDirDown => dir_sizes.push(0),
-			// If we're going up in directories (such as with `cd ..`), pop this directory off,
-			// and add it to the sum if it's under MAX_SIZE
-			Listing::ChangeDirUp => {
-				let size = dir_sizes.pop().unwrap();
-
-				// Each directory above this one also has the size of this directory
-				let upper_size = dir_sizes.last_mut().unwrap();
-				*upper_size += size;
-
-				if size <= MAX_SIZE {
+	// Parse each line, then follow each listing
+	listings(lines).for_each(|listing| match listing {
+		// If we're going back to the root directory (with `cd /`), go up in directories until we reach it, as below
+		Listing::ChangeDirDown(name) if name == "/" && !dir_sizes.is_empty() => {
+			while let Some(size) = leave_dir(&mut dir_sizes) {
+				// The directory which was left was just past the end of the list
+				if size <= MAX_SIZE && dir_sizes.len() <= max_depth {
 					sum += size;
 				}
 			}
-			// Otherwise, if we're looking at a file entry, add its size to the current directory
-			Listing::File(size) => *dir_sizes.last_mut().unwrap() += size,
-		});
+		}
+		// If we're going down in directories (such as with `cd a`), add a new empty directory
+		Listing::ChangeDirDown(_) => dir_sizes.push(0),
+		// If we're going up in directories (such as with `cd ..`), pop this directory off,
+		// and add it to the sum if it's under MAX_SIZE and not too deep
+		Listing::ChangeDirUp => {
+			if let Some(size) = leave_dir(&mut dir_sizes) {
+				if size <= MAX_SIZE && dir_sizes.len() <= max_depth {
+					sum += size;
+				}
+			}
+		}
+		// Otherwise, if we're looking at a file entry, add its size to the current directory
+		Listing::File(_, size) => *dir_sizes.last_mut().unwrap() += size,
+	});
 
 	// Once we're done with all of the listings, we're left with a bunch of directories which
 	// were never navigated out of, so we need to process them to.
 	sum += dir_sizes
 		.iter()
+		// Keep track of the depth of each directory
+		.enumerate()
 		// Reverse order because we've been using dir_sizes like a stack
 		.rev()
 		// Because we never popped these directories out, they never had the sizes of their last child
 		// added to their size (see above), so we need to do that here. acc keeps track of the size of the
 		// last child, and starts at 0 since this last directory has no unprocessed children
-		.scan(0, |acc, size| {
+		.scan(0, |acc, (depth, size)| {
 			*acc += size;
 
 			if *acc <= MAX_SIZE {
-				Some(*acc)
+				Some((depth, *acc))
 			} else {
 				None
 			}
 		})
-		// Then add these directories to the sum
+		// Then add the directories which aren't too deep to the sum
+		.filter(|(depth, _)| *depth <= max_depth)
+		.map(|(_, size)| size)
 		.sum::<u64>();
 
 	sum
 }
 
-fn smallest_deletable_dir<T: Iterator<Item = String>>(lines: T) -> u64 {
-	// The total space on the drive
-	const TOTAL_SPACE: u64 = 70_000_000;
-	// How much free space we want to end up with
-	const FREE_SPACE: u64 = 30_000_000;
+/// The sizes of every directory (including everything inside of it) at most `max_depth` levels below the root directory
+/// in post-order traversal order, from a list of commands navigating directories. The root directory is always last.
+fn all_dir_sizes<T: Iterator<Item = String>>(lines: T, max_depth: usize) -> Vec<u64> {
 	// A list of sizes of a directory and all of its parent directories, in reverse order
 	let mut dir_sizes = Vec::new();
 
@@ -148,29 +290,51 @@ fn smallest_deletable_dir<T: Iterator<Item = String>>(lines: T) -> u64 {
 
 	// The same as above in total_size, except that instead of summing sizes,
 	// we push them in all_dir_sizes to be processed later.
-	lines
-		.flat_map(|line| line.parse::<Listing>())
-		.for_each(|listing| match listing {
-			Listing::ChangeDirDown => dir_sizes.push(0),
-			Listing::ChangeDirUp => {
-				let size = dir_sizes.pop().unwrap();
-
-				let upper_size = dir_sizes.last_mut().unwrap();
-
-				*upper_size += size;
-
+	let mut leave = |dir_sizes: &mut Vec<u64>| {
+		if let Some(size) = leave_dir(dir_sizes) {
+			if dir_sizes.len() <= max_depth {
 				all_dir_sizes.push(size);
 			}
-			Listing::File(size) => *dir_sizes.last_mut().unwrap() += size,
-		});
+		}
+	};
+	listings(lines).for_each(|listing| match listing {
+		Listing::ChangeDirDown(name) if name == "/" && !dir_sizes.is_empty() => {
+			while dir_sizes.len() > 1 {
+				leave(&mut dir_sizes);
+			}
+		}
+		Listing::ChangeDirDown(_) => dir_sizes.push(0),
+		Listing::ChangeDirUp => leave(&mut dir_sizes),
+		Listing::File(_, size) => *dir_sizes.last_mut().unwrap() += size,
+	});
 
 	// Similarly to above, we need to process the remaining leftover directories we didn't back
 	// out of at the end of the listings. We'll add those on to the end of all_dir_sizes
-	all_dir_sizes.extend(dir_sizes.iter().rev().scan(0, |acc, size| {
-		*acc += size;
+	all_dir_sizes.extend(
+		dir_sizes
+			.iter()
+			.enumerate()
+			.rev()
+			.scan(0, |acc, (depth, size)| {
+				*acc += size;
 
-		Some(*acc)
-	}));
+				Some((depth, *acc))
+			})
+			.filter(|(depth, _)| *depth <= max_depth)
+			.map(|(_, size)| size),
+	);
+
+	all_dir_sizes
+}
+
+/// The size of the smallest directory at most `max_depth` levels below the root directory which frees up enough space if deleted
+fn smallest_deletable_dir<T: Iterator<Item = String>>(lines: T, max_depth: usize) -> u64 {
+	// The total space on the drive
+	const TOTAL_SPACE: u64 = 70_000_000;
+	// How much free space we want to end up with
+	const FREE_SPACE: u64 = 30_000_000;
+
+	let all_dir_sizes = all_dir_sizes(lines, max_depth);
 
 	// The total size everything is taking up is the size of the / directory, which should be the last directory
 	// in all_dir_sizes since it is in post-order traversal order
@@ -186,8 +350,330 @@ fn smallest_deletable_dir<T: Iterator<Item = String>>(lines: T) -> u64 {
 		.unwrap()
 }
 
+/// A single directory in a [`FileSystem`]
+struct Directory {
+	name: String,
+	/// The index of this directory's parent in the file system, or `None` for the root directory
+	parent: Option<usize>,
+	/// The directories directly inside this one, by name, as indices into the file system
+	children: BTreeMap<String, usize>,
+	/// The files directly inside this directory, by name, with their sizes
+	files: BTreeMap<String, u64>,
+}
+
+/// An entry in a [`Directory`], which is either another directory (as an index into the file system) or a file with its size
+enum Entry {
+	Dir(usize),
+	File(u64),
+}
+
+/// A single directory or file in the tree view of a [`FileSystem`], for printing as JSON.
+/// Files are the leaves of the tree, and have no children.
+#[derive(Serialize, Deserialize)]
+struct TreeNode {
+	name: String,
+	/// The cumulative size of a directory, or the size of a file
+	size: u64,
+	/// The directories and files inside of a directory, sorted by name
+	children: Vec<TreeNode>,
+}
+
+/// A tree of named directories and files, reconstructed from the listings of a terminal session.
+/// Unlike [`total_size`] and [`smallest_deletable_dir`], which only keep track of the sizes of the directories
+/// currently being traversed, this remembers the whole filesystem so that it can be queried afterwards.
+///
+/// Directories are stored in a flat list, starting with the root directory. Directories are only ever created
+/// once their parent exists, so each directory comes after its parent in the list.
+struct FileSystem {
+	dirs: Vec<Directory>,
+}
+
+impl FileSystem {
+	/// The index of the root directory
+	const ROOT: usize = 0;
+
+	/// Build the filesystem by following the listings of a terminal session.
+	/// The first directory changed into becomes the root directory, taking its name.
+	/// A directory is only ever created inside of the directory it was changed into from, and `cd .` doesn't go anywhere
+	/// (see [`Listing::split_path`]), so the tree never loops back on itself.
+	fn from_session<T: Iterator<Item = String>>(lines: T) -> Result<Self> {
+		let mut fs = FileSystem {
+			dirs: vec![Directory {
+				name: "/".to_string(),
+				parent: None,
+				children: BTreeMap::new(),
+				files: BTreeMap::new(),
+			}],
+		};
+		// The directory we're currently in. None until the session changes into its first directory,
+		// so the name of the root directory is only a placeholder until then.
+		let mut cwd = None;
+
+		for listing in listings(lines) {
+			match listing {
+				Listing::ChangeDirDown(name) => {
+					cwd = Some(match cwd {
+						// The first directory the session changes into is the root, whatever it's called
+						None => {
+							fs.dirs[Self::ROOT].name = name;
+							Self::ROOT
+						}
+						// After that, `cd /` always goes back to the root
+						Some(_) if name == "/" => Self::ROOT,
+						Some(cwd) => fs.child(cwd, name),
+					});
+				}
+				// As in a shell, there's nothing above the root directory, so `cd ..` there stays in the root directory
+				Listing::ChangeDirUp => {
+					if let Some(parent) = cwd.and_then(|cwd| fs.dirs[cwd].parent) {
+						cwd = Some(parent);
+					}
+				}
+				Listing::File(name, size) => {
+					let cwd = cwd.ok_or_else(|| {
+						anyhow!("Listed file `{name}` before changing into a directory")
+					})?;
+					fs.dirs[cwd].files.insert(name, size);
+				}
+			}
+		}
+
+		Ok(fs)
+	}
+
+	/// Find the child directory of `parent` called `name`, creating it if it doesn't exist yet
+	fn child(&mut self, parent: usize, name: String) -> usize {
+		if let Some(&idx) = self.dirs[parent].children.get(&name) {
+			return idx;
+		}
+
+		let idx = self.dirs.len();
+		self.dirs[parent].children.insert(name.clone(), idx);
+		self.dirs.push(Directory {
+			name,
+			parent: Some(parent),
+			children: BTreeMap::new(),
+			files: BTreeMap::new(),
+		});
+
+		idx
+	}
+
+	/// Find the directory at a path of directory names, starting from the root directory (such as `/a/e`)
+	fn find(&self, path: &str) -> Result<usize> {
+		path.split('/')
+			// Ignore the leading `/`, as well as any doubled or trailing `/`s
+			.filter(|name| !name.is_empty())
+			.try_fold(Self::ROOT, |idx, name| {
+				self.dirs[idx]
+					.children
+					.get(name)
+					.copied()
+					.ok_or_else(|| anyhow!("No directory `{name}` on the way to `{path}`"))
+			})
+	}
+
+	/// The cumulative size of the directory at a path, as in [`FileSystem::find`]
+	fn size_of(&self, path: &str) -> Result<u64> {
+		let idx = self.find(path)?;
+
+		Ok(self.dir_sizes()[idx])
+	}
+
+	/// The path from the root directory to a directory, in the same form as [`FileSystem::find`] takes
+	fn path_of(&self, mut idx: usize) -> String {
+		let mut names = Vec::new();
+		// Walk up to the root, which is always called `/` in paths whatever its name
+		while let Some(parent) = self.dirs[idx].parent {
+			names.push(self.dirs[idx].name.as_str());
+			idx = parent;
+		}
+
+		if names.is_empty() {
+			return "/".to_string();
+		}
+
+		names
+			.iter()
+			.rev()
+			.fold(String::new(), |path, name| path + "/" + name)
+	}
+
+	/// How many levels below the root directory a directory is. The root directory is at level 0
+	fn depth_of(&self, mut idx: usize) -> usize {
+		let mut depth = 0;
+		while let Some(parent) = self.dirs[idx].parent {
+			depth += 1;
+			idx = parent;
+		}
+
+		depth
+	}
+
+	/// The path and cumulative size of every directory with a size in `min..=max`, and which is at most `max_depth`
+	/// levels below the root directory, sorted by path
+	fn dirs_in_range(&self, min: u64, max: u64, max_depth: usize) -> Vec<(String, u64)> {
+		let mut dirs = self
+			.dir_sizes()
+			.into_iter()
+			.enumerate()
+			.filter(|(idx, size)| (min..=max).contains(size) && self.depth_of(*idx) <= max_depth)
+			.map(|(idx, size)| (self.path_of(idx), size))
+			.collect::<Vec<_>>();
+		dirs.sort_unstable();
+
+		dirs
+	}
+
+	/// Count the directories whose size is at least `threshold`, and which are at most `max_depth` levels below the root directory
+	fn count_large_dirs(&self, threshold: u64, max_depth: usize) -> usize {
+		self.dirs_in_range(threshold, u64::MAX, max_depth).len()
+	}
+
+	/// Sum the sizes of the directories with a size in `min..=max`, and which are at most `max_depth` levels below the root directory.
+	/// A generalization of [`total_size`], which sums the directories in `0..=100_000`, except that a directory which the session
+	/// goes into more than once is only counted once.
+	fn sum_between(&self, min: u64, max: u64, max_depth: usize) -> u64 {
+		self.dirs_in_range(min, max, max_depth)
+			.iter()
+			.map(|(_, size)| size)
+			.sum()
+	}
+
+	/// The path and cumulative size of every directory which has no directories inside of it, and which is at most `max_depth`
+	/// levels below the root directory, sorted by path. Files don't stop a directory from being a leaf.
+	fn leaf_dirs(&self, max_depth: usize) -> Vec<(String, u64)> {
+		let mut dirs = self
+			.dir_sizes()
+			.into_iter()
+			.enumerate()
+			.filter(|(idx, _)| {
+				self.dirs[*idx].children.is_empty() && self.depth_of(*idx) <= max_depth
+			})
+			.map(|(idx, size)| (self.path_of(idx), size))
+			.collect::<Vec<_>>();
+		dirs.sort_unstable();
+
+		dirs
+	}
+
+	/// Render the size and path of every directory at most `max_depth` levels below the root directory in the same format as
+	/// `du -b --max-depth` (`<size>\t<path>`), one per line, sorted by path
+	fn render_du(&self, max_depth: usize) -> String {
+		self.dirs_in_range(0, u64::MAX, max_depth).into_iter().fold(
+			String::new(),
+			|mut out, (path, size)| {
+				// Writing to a String can't fail
+				writeln!(out, "{size}\t{path}").unwrap();
+				out
+			},
+		)
+	}
+
+	/// The cumulative size of every directory (including everything inside of it), in the same order as `dirs`
+	fn dir_sizes(&self) -> Vec<u64> {
+		let mut sizes = self
+			.dirs
+			.iter()
+			.map(|dir| dir.files.values().sum())
+			.collect::<Vec<u64>>();
+
+		// Every directory comes after its parent, so by going backwards we know each directory
+		// has already had all of its children added to it by the time we add it to its parent
+		for (idx, dir) in self.dirs.iter().enumerate().rev() {
+			if let Some(parent) = dir.parent {
+				sizes[parent] += sizes[idx];
+			}
+		}
+
+		sizes
+	}
+
+	/// Render the filesystem as an indented tree, with each entry on its own line and entries in a directory sorted by name:
+	/// ```text
+	/// - / (dir, size=48381165)
+	///   - a (dir, size=94853)
+	///     - e (dir, size=584)
+	///       - i (file, size=584)
+	/// ```
+	fn render_tree(&self) -> String {
+		let sizes = self.dir_sizes();
+		let mut out = String::new();
+		self.render_dir(Self::ROOT, 0, &sizes, &mut out);
+
+		out
+	}
+
+	/// The subdirectories and files directly inside of a directory, merged together in name order
+	fn entries(&self, idx: usize) -> Vec<(&String, Entry)> {
+		let dir = &self.dirs[idx];
+
+		let mut entries = dir
+			.children
+			.iter()
+			.map(|(name, child)| (name, Entry::Dir(*child)))
+			.chain(
+				dir.files
+					.iter()
+					.map(|(name, size)| (name, Entry::File(*size))),
+			)
+			.collect::<Vec<_>>();
+		entries.sort_unstable_by_key(|(name, _)| *name);
+
+		entries
+	}
+
+	/// Render a single directory and everything inside of it at a certain depth from the root
+	fn render_dir(&self, idx: usize, depth: usize, sizes: &[u64], out: &mut String) {
+		let indent = "  ".repeat(depth);
+
+		// Writing to a String can't fail
+		writeln!(
+			out,
+			"{indent}- {} (dir, size={})",
+			self.dirs[idx].name, sizes[idx]
+		)
+		.unwrap();
+
+		for (name, entry) in self.entries(idx) {
+			match entry {
+				Entry::Dir(child) => self.render_dir(child, depth + 1, sizes, out),
+				Entry::File(size) => {
+					writeln!(out, "{indent}  - {name} (file, size={size})").unwrap();
+				}
+			}
+		}
+	}
+
+	/// The same tree as [`FileSystem::render_tree`], as nodes which can be printed as JSON
+	fn to_tree(&self) -> TreeNode {
+		self.dir_node(Self::ROOT, &self.dir_sizes())
+	}
+
+	/// A single directory and everything inside of it as a [`TreeNode`]
+	fn dir_node(&self, idx: usize, sizes: &[u64]) -> TreeNode {
+		TreeNode {
+			name: self.dirs[idx].name.clone(),
+			size: sizes[idx],
+			children: self
+				.entries(idx)
+				.into_iter()
+				.map(|(name, entry)| match entry {
+					Entry::Dir(child) => self.dir_node(child, sizes),
+					Entry::File(size) => TreeNode {
+						name: name.clone(),
+						size,
+						children: Vec::new(),
+					},
+				})
+				.collect(),
+		}
+	}
+}
+
 fn main() -> Result<()> {
 	let args = Args::parse();
+	let max_depth = args.max_depth.unwrap_or(usize::MAX);
 
 	let file = File::open(args.input_file)?;
 	let lines = io::BufReader::with_capacity(10_000_000, file)
@@ -195,12 +681,86 @@ fn main() -> Result<()> {
 		// Skip lines which couldn't be read
 		.flatten();
 
-	let size = match args.mode {
-		Mode::SmallDirSize => total_size(lines),
-		Mode::FreeSpace => smallest_deletable_dir(lines),
+	// Checking the start of the session means going through the lines twice, so only collect them when necessary
+	let lines: Box<dyn Iterator<Item = String>> = if args.strict {
+		let lines = lines.collect::<Vec<_>>();
+		check_first_cd(&lines)?;
+
+		Box::new(lines.into_iter())
+	} else {
+		Box::new(lines)
 	};
 
-	println!("{size}");
+	if args.tree {
+		let fs = FileSystem::from_session(lines)?;
+
+		match args.format {
+			Format::Plain => print!("{}", fs.render_tree()),
+			Format::Json => println!("{}", serde_json::to_string(&fs.to_tree())?),
+		}
+
+		return Ok(());
+	}
+
+	if args.du {
+		print!("{}", FileSystem::from_session(lines)?.render_du(max_depth));
+
+		return Ok(());
+	}
+
+	if let Some(path) = args.path {
+		println!("{}", FileSystem::from_session(lines)?.size_of(&path)?);
+
+		return Ok(());
+	}
+
+	if args.min_size.is_some() || args.max_size.is_some() {
+		let fs = FileSystem::from_session(lines)?;
+		let dirs = fs.dirs_in_range(
+			args.min_size.unwrap_or(0),
+			args.max_size.unwrap_or(u64::MAX),
+			max_depth,
+		);
+
+		for (path, size) in dirs {
+			println!("{path} {size}");
+		}
+
+		return Ok(());
+	}
+
+	if let Some(between) = args.between {
+		// Clap makes sure there are exactly two sizes
+		let (min, max) = (between[0], between[1]);
+		if min > max {
+			return Err(anyhow!(
+				"The smallest size {min} is larger than the largest size {max}"
+			));
+		}
+
+		println!(
+			"{}",
+			FileSystem::from_session(lines)?.sum_between(min, max, max_depth)
+		);
+
+		return Ok(());
+	}
+
+	// Mode is only optional when querying the filesystem tree, so it must be present here
+	match args.mode.unwrap() {
+		Mode::SmallDirSize => println!("{}", total_size(lines, max_depth)),
+		Mode::FreeSpace => println!("{}", smallest_deletable_dir(lines, max_depth)),
+		Mode::CountLarge => println!(
+			"{}",
+			FileSystem::from_session(lines)?.count_large_dirs(args.threshold, max_depth)
+		),
+		// Which directories are leaves isn't known until the whole session has been read, so this needs the whole tree
+		Mode::LeafDirSize => {
+			let leaves = FileSystem::from_session(lines)?.leaf_dirs(max_depth);
+
+			println!("{}", leaves.iter().map(|(_, size)| size).sum::<u64>());
+		}
+	}
 
 	Ok(())
 }
@@ -238,7 +798,404 @@ $ ls
 	fn example() {
 		let lines = PROMPT.lines().map(std::string::ToString::to_string);
 
-		assert_eq!(total_size(lines.clone()), 95437);
-		assert_eq!(smallest_deletable_dir(lines), 24_933_642);
+		assert_eq!(total_size(lines.clone(), usize::MAX), 95437);
+		assert_eq!(smallest_deletable_dir(lines, usize::MAX), 24_933_642);
+	}
+
+	#[test]
+	fn large_dirs() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+
+		// `/` and `d` - `a` is just short of 100,000
+		assert_eq!(
+			FileSystem::from_session(lines.clone())
+				.unwrap()
+				.count_large_dirs(100_000, usize::MAX),
+			2
+		);
+		assert_eq!(
+			FileSystem::from_session(lines.clone())
+				.unwrap()
+				.count_large_dirs(94853, usize::MAX),
+			3
+		);
+		assert_eq!(
+			FileSystem::from_session(lines)
+				.unwrap()
+				.count_large_dirs(0, usize::MAX),
+			4
+		);
+	}
+
+	#[test]
+	fn tree() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+		let fs = FileSystem::from_session(lines).unwrap();
+
+		assert_eq!(
+			fs.render_tree(),
+			"- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+"
+		);
+	}
+
+	#[test]
+	fn named_root() {
+		let session = "$ cd home
+$ ls
+dir a
+100 b.txt
+$ cd a
+$ ls
+dir e
+20 f
+$ cd e
+$ ls
+3 g
+$ cd /
+$ cd a
+$ ls
+20 f
+4 h";
+		let lines = session.lines().map(std::string::ToString::to_string);
+		let fs = FileSystem::from_session(lines).unwrap();
+
+		assert_eq!(
+			fs.render_tree(),
+			"- home (dir, size=127)
+  - a (dir, size=27)
+    - e (dir, size=3)
+      - g (file, size=3)
+    - f (file, size=20)
+    - h (file, size=4)
+  - b.txt (file, size=100)
+"
+		);
+	}
+
+	#[test]
+	fn path_size() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+		let fs = FileSystem::from_session(lines).unwrap();
+
+		assert_eq!(fs.size_of("/a/e").unwrap(), 584);
+		assert_eq!(fs.size_of("/d").unwrap(), 24_933_642);
+		assert_eq!(fs.size_of("/").unwrap(), 48_381_165);
+		assert!(fs.size_of("/a/x").is_err());
+	}
+
+	#[test]
+	fn size_range() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+		let fs = FileSystem::from_session(lines).unwrap();
+
+		assert_eq!(
+			fs.dirs_in_range(0, 100_000, usize::MAX),
+			[("/a".to_string(), 94853), ("/a/e".to_string(), 584)]
+		);
+		assert_eq!(
+			fs.dirs_in_range(1_000_000, u64::MAX, usize::MAX),
+			[
+				("/".to_string(), 48_381_165),
+				("/d".to_string(), 24_933_642)
+			]
+		);
+	}
+
+	#[test]
+	fn compound_cd() {
+		let session = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a/e
+$ ls
+584 i
+$ cd ..
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd ../d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+		let lines = session.lines().map(std::string::ToString::to_string);
+
+		assert_eq!(total_size(lines.clone(), usize::MAX), 95437);
+		assert_eq!(
+			smallest_deletable_dir(lines.clone(), usize::MAX),
+			24_933_642
+		);
+
+		let fs = FileSystem::from_session(lines).unwrap();
+		let expected =
+			FileSystem::from_session(PROMPT.lines().map(std::string::ToString::to_string)).unwrap();
+		assert_eq!(fs.render_tree(), expected.render_tree());
+	}
+
+	#[test]
+	fn du() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+		let du = FileSystem::from_session(lines)
+			.unwrap()
+			.render_du(usize::MAX);
+
+		assert_eq!(du.lines().count(), 4);
+		assert_eq!(du.lines().next(), Some("48381165\t/"));
+		assert!(du.lines().any(|line| line == "584\t/a/e"));
+	}
+
+	#[test]
+	fn one_query() {
+		let parse = |args: &[&str]| Args::try_parse_from(["directories"].iter().chain(args));
+
+		assert!(parse(&["--du", "--path", "/a"]).is_err());
+		assert!(parse(&["small-dir-size", "--tree"]).is_err());
+		assert!(parse(&["--between", "1", "2", "--du"]).is_err());
+		assert!(parse(&["--min-size", "5", "--du"]).is_err());
+		assert!(parse(&["--max-size", "5", "free-space"]).is_err());
+
+		assert!(parse(&["--min-size", "1", "--max-size", "5"]).is_ok());
+		assert!(parse(&["--tree", "--format", "json"]).is_ok());
+		assert!(parse(&["count-large", "--threshold", "5"]).is_ok());
+		assert!(parse(&[]).is_err());
+	}
+
+	#[test]
+	fn between() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+
+		// Only `a` - `e` is too small
+		assert_eq!(
+			FileSystem::from_session(lines.clone())
+				.unwrap()
+				.sum_between(1000, 100_000, usize::MAX),
+			94853
+		);
+		assert_eq!(
+			FileSystem::from_session(lines.clone())
+				.unwrap()
+				.sum_between(0, 100_000, usize::MAX),
+			95437
+		);
+		assert_eq!(
+			FileSystem::from_session(lines.clone())
+				.unwrap()
+				.sum_between(0, 100_000, usize::MAX),
+			total_size(lines.clone(), usize::MAX)
+		);
+		assert_eq!(
+			FileSystem::from_session(lines)
+				.unwrap()
+				.sum_between(584, 584, usize::MAX),
+			584
+		);
+
+		// Going into `a` twice doesn't count it twice, the same as listing the directories in a range of sizes
+		let session = "$ cd /
+$ cd a
+$ ls
+5 x
+$ cd ..
+$ cd a
+$ ls
+5 x";
+		let fs = FileSystem::from_session(session.lines().map(std::string::ToString::to_string))
+			.unwrap();
+		assert_eq!(fs.sum_between(0, 1_000_000, usize::MAX), 10);
+		assert_eq!(
+			fs.dirs_in_range(0, 100_000, usize::MAX),
+			[("/".to_string(), 5), ("/a".to_string(), 5)]
+		);
+		assert_eq!(fs.count_large_dirs(5, usize::MAX), 2);
+	}
+
+	#[test]
+	fn max_depth() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+		let fs = FileSystem::from_session(lines.clone()).unwrap();
+
+		// `/a/e` is the only directory two levels down
+		assert_eq!(
+			fs.dirs_in_range(0, u64::MAX, 1),
+			[
+				("/".to_string(), 48_381_165),
+				("/a".to_string(), 94853),
+				("/d".to_string(), 24_933_642)
+			]
+		);
+		assert_eq!(fs.render_du(0), "48381165\t/\n");
+		assert_eq!(
+			all_dir_sizes(lines.clone(), 1),
+			[94853, 24_933_642, 48_381_165]
+		);
+		assert_eq!(total_size(lines.clone(), 1), 94853);
+		assert_eq!(
+			FileSystem::from_session(lines.clone())
+				.unwrap()
+				.count_large_dirs(0, 1),
+			3
+		);
+		assert_eq!(smallest_deletable_dir(lines, 0), 48_381_165);
+	}
+
+	#[test]
+	fn leaf_dirs() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+		let fs = FileSystem::from_session(lines).unwrap();
+
+		// `a` has `e` inside of it, but `e` and `d` only have files
+		assert_eq!(
+			fs.leaf_dirs(usize::MAX),
+			[("/a/e".to_string(), 584), ("/d".to_string(), 24_933_642)]
+		);
+		assert_eq!(
+			fs.leaf_dirs(usize::MAX)
+				.iter()
+				.map(|(_, size)| size)
+				.sum::<u64>(),
+			584 + 24_933_642
+		);
+		assert_eq!(fs.leaf_dirs(1), [("/d".to_string(), 24_933_642)]);
+
+		// A root directory with no directories inside of it is a leaf itself
+		let fs =
+			FileSystem::from_session(["$ cd /", "$ ls", "10 a"].map(str::to_string).into_iter())
+				.unwrap();
+		assert_eq!(fs.leaf_dirs(usize::MAX), [("/".to_string(), 10)]);
+	}
+
+	#[test]
+	fn current_dir() {
+		// `cd .` stays in `a`, the same as the `.` in `cd ./b`, rather than going into a new directory
+		let session = "$ cd /
+$ cd a
+$ cd .
+$ ls
+10 c
+$ cd ./b
+$ ls
+20 d
+$ cd ..
+$ cd ..";
+		let lines = || session.lines().map(std::string::ToString::to_string);
+
+		let fs = FileSystem::from_session(lines()).unwrap();
+		assert_eq!(fs.size_of("/a").unwrap(), 30);
+		assert_eq!(fs.size_of("/a/b").unwrap(), 20);
+		assert_eq!(fs.dirs.len(), 3);
+
+		assert_eq!(all_dir_sizes(lines(), usize::MAX), [20, 30, 30]);
+		assert_eq!(total_size(lines(), usize::MAX), 80);
+	}
+
+	#[test]
+	fn json_tree() {
+		let lines = PROMPT.lines().map(std::string::ToString::to_string);
+		let fs = FileSystem::from_session(lines).unwrap();
+
+		let json = serde_json::to_string(&fs.to_tree()).unwrap();
+		let root: TreeNode = serde_json::from_str(&json).unwrap();
+
+		assert_eq!(root.name, "/");
+		assert_eq!(root.size, 48_381_165);
+
+		// Children are in name order, so `a` comes first, and `e` is the first thing inside of it
+		let a = &root.children[0];
+		assert_eq!((a.name.as_str(), a.size), ("a", 94853));
+		let e = &a.children[0];
+		assert_eq!((e.name.as_str(), e.size), ("e", 584));
+		assert_eq!(e.children.len(), 1);
+		assert!(e.children[0].children.is_empty());
+	}
+
+	#[test]
+	fn implicit_root() {
+		// The same as the prompt, except that the files in the root directory are listed before changing into it
+		let session = "$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd /
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+		let lines = session.lines().map(std::string::ToString::to_string);
+
+		assert_eq!(total_size(lines.clone(), usize::MAX), 95437);
+		assert_eq!(
+			smallest_deletable_dir(lines.clone(), usize::MAX),
+			24_933_642
+		);
+		assert_eq!(
+			FileSystem::from_session(lines.clone())
+				.unwrap()
+				.count_large_dirs(0, usize::MAX),
+			4
+		);
+
+		let fs = FileSystem::from_session(lines.clone()).unwrap();
+		let expected =
+			FileSystem::from_session(PROMPT.lines().map(std::string::ToString::to_string)).unwrap();
+		assert_eq!(fs.render_tree(), expected.render_tree());
+
+		// There's nothing above the root directory, so `cd ..` from it stays there
+		let session = "$ cd /
+$ ls
+10 x
+$ cd ..
+$ cd a
+$ ls
+20 y
+$ cd ..
+$ cd ..";
+		let up_from_root = || session.lines().map(std::string::ToString::to_string);
+		assert_eq!(total_size(up_from_root(), usize::MAX), 50);
+		assert_eq!(all_dir_sizes(up_from_root(), usize::MAX), [20, 30]);
+		let fs = FileSystem::from_session(up_from_root()).unwrap();
+		assert_eq!(fs.size_of("/").unwrap(), 30);
+		assert_eq!(fs.size_of("/a").unwrap(), 20);
+
+		// Only an error when strict
+		assert!(check_first_cd(&lines.collect::<Vec<_>>()).is_err());
+		assert!(check_first_cd(
+			&PROMPT
+				.lines()
+				.map(std::string::ToString::to_string)
+				.collect::<Vec<_>>()
+		)
+		.is_ok());
 	}
 }
@@ -1,7 +1,11 @@
 #![deny(clippy::pedantic)]
-#![feature(get_many_mut)]
 #![feature(let_chains)]
-use std::{fs::File, io::Read, path::PathBuf, str::FromStr};
+use std::{
+	fs::File,
+	io::{self, Read, Write},
+	path::PathBuf,
+	str::FromStr,
+};
 
 use anyhow::{anyhow, Result};
 use clap::{Parser, ValueEnum};
@@ -12,16 +16,77 @@ enum Mode {
 	NumVisible,
 	/// The second variant of the problem, wher we find the highest scenic score possible out of all the trees.
 	ScenicScore,
+	/// Add up the scenic scores of every tree, as a measure of the forest as a whole
+	ScenicSum,
+	/// Both variants of the problem at once, going through the grid only once
+	Both,
+	/// Multiply together the heights of every tree on the edges of the forest (wrapping around at 2^64), as a fingerprint of the input
+	PerimeterProduct,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+/// An edge of the forest which trees can be seen from
+enum Edge {
+	Top,
+	Left,
+	Right,
+	Bottom,
+	/// Visible from any edge, as in the original problem
+	Any,
 }
 
 #[derive(Parser)]
 struct Args {
-	/// Input file path
+	/// Input file path. Use `-` to read the grid from stdin
 	#[arg(short, long, default_value = "input.txt")]
 	input_file: PathBuf,
 	/// What mode to run the program in
 	#[arg(value_enum)]
 	mode: Mode,
+	/// In num-visible mode, only count trees which are visible from this edge of the forest
+	#[arg(long, value_enum, default_value_t = Edge::Any)]
+	direction: Edge,
+	/// Also print the number of trees on the edges of the forest, which are always visible
+	#[arg(long)]
+	edges: bool,
+	/// In scenic-score mode, print the K highest scenic scores along with the row and column of their trees
+	#[arg(long, value_name = "K")]
+	top_k: Option<usize>,
+	/// In scenic-score mode, print the row, column, and scenic score of every tree with a scenic score of at least N
+	#[arg(long, value_name = "N", conflicts_with = "top_k")]
+	min_scenic: Option<u128>,
+	/// In scenic-score mode, also look diagonally from each tree, multiplying the diagonal view distances into the scenic score
+	#[arg(long)]
+	diagonals: bool,
+	/// In scenic-score mode, treat the forest as wrapping around, so that looking past an edge continues from the opposite edge.
+	/// Sight stops before it gets back around to the tree it started from
+	#[arg(long)]
+	wrap: bool,
+	/// Also write a grayscale PPM image to this path, where brighter trees have higher scenic scores.
+	/// In num-visible mode, visible trees are white and hidden trees are black instead, and in perimeter-product mode taller trees are brighter
+	#[arg(long, value_name = "PATH")]
+	image: Option<PathBuf>,
+	#[command(flatten)]
+	visible: VisibleArgs,
+}
+
+#[derive(clap::Args)]
+struct VisibleArgs {
+	/// In num-visible mode, count the trees which are hidden from the edge instead of the ones which are visible
+	#[arg(long)]
+	invert: bool,
+	/// In num-visible mode, also list the row and column of every tree which was counted
+	#[arg(long)]
+	list: bool,
+}
+
+#[derive(Clone, Copy)]
+/// Which way a [`TreeGrid::sweep`] is going through the grid
+enum Pass {
+	/// From the top left to the bottom right, row by row
+	Forward,
+	/// From the bottom right to the top left, row by row
+	Backward,
 }
 
 struct TreeGrid {
@@ -29,117 +94,231 @@ struct TreeGrid {
 	width: usize,
 }
 
+impl TreeGrid {
+	/// The number of rows of trees in the grid
+	fn height(&self) -> usize {
+		self.heights.len() / self.width
+	}
+
+	/// Visit every tree in the grid twice - once going forwards through the grid, and then once going backwards.
+	/// When a tree is visited, the trees above,left of it (going forwards) or below,right of it (going backwards)
+	/// have already been visited in the same pass, so their partial results can be built upon.
+	fn sweep(&self, mut visit: impl FnMut(Pass, usize)) {
+		for i in 0..self.heights.len() {
+			visit(Pass::Forward, i);
+		}
+		for i in (0..self.heights.len()).rev() {
+			visit(Pass::Backward, i);
+		}
+	}
+}
+
+/// Whether the tree at index `idx` is on the edge of a grid with the given dimensions
+fn is_edge(idx: usize, width: usize, height: usize) -> bool {
+	let (row, col) = (idx / width, idx % width);
+
+	row == 0 || row == height - 1 || col == 0 || col == width - 1
+}
+
+/// The number of trees on the edges of a grid with the given dimensions
+fn edge_count(width: usize, height: usize) -> usize {
+	(0..width * height)
+		.filter(|idx| is_edge(*idx, width, height))
+		.count()
+}
+
+/// The product of the heights of every tree on the edges of the grid, wrapping around if it doesn't fit in a u64
+fn perimeter_product(tree_grid: &TreeGrid) -> u64 {
+	tree_grid
+		.heights
+		.iter()
+		.enumerate()
+		// Only the trees on the edges
+		.filter(|(idx, _)| is_edge(*idx, tree_grid.width, tree_grid.height()))
+		.fold(1, |product, (_, height)| {
+			product.wrapping_mul(u64::from(*height))
+		})
+}
+
 impl FromStr for TreeGrid {
 	type Err = anyhow::Error;
 
 	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-		let width = s.lines().next().ok_or_else(|| anyhow!("No lines"))?.len();
+		let mut heights = Vec::new();
+		let mut width = None;
+
+		// Parse row by row, ignoring any whitespace around each row (such as trailing spaces or carriage returns),
+		// and any blank lines after the last row
+		for (row, line) in s.trim_end().lines().map(str::trim).enumerate() {
+			if line.is_empty() {
+				return Err(anyhow!("Row {} is empty", row + 1));
+			}
+
+			// Every row must be as wide as the first one
+			let row_width = line.chars().count();
+			match width {
+				None => width = Some(row_width),
+				Some(width) if width != row_width => {
+					return Err(anyhow!(
+						"Row {} has {row_width} trees, but the first row has {width}",
+						row + 1
+					))
+				}
+				Some(_) => {}
+			}
+
+			for c in line.chars() {
+				let height = c
+					.to_digit(10)
+					.ok_or_else(|| anyhow!("`{c}` in row {} is not a tree height", row + 1))?;
+				heights.push(u8::try_from(height)?);
+			}
+		}
+
 		Ok(TreeGrid {
-			heights: s
-				.as_bytes()
-				.iter()
-				.filter(|c| !c.is_ascii_whitespace())
-				.map(|c| {
-					c.checked_sub(b'0')
-						.unwrap_or_else(|| panic!("Couldn't subtract from {}", *c as char))
-				})
-				.collect(),
-			width,
+			heights,
+			width: width.ok_or_else(|| anyhow!("No lines"))?,
 		})
 	}
 }
 
 mod part1 {
-	use super::TreeGrid;
-	pub(super) fn visible_trees(tree_grid: &TreeGrid) -> usize {
-		// Convert the tree grid to a grid of visibilities -
-		// 3-tuples that indicate if a tree in the grid is visible, and the
-		// tallest tree in each of two directions. We'll fill the heights in as
-		// we go, so by default the tallest trees we know about are the trees in
-		// those positions
-		let mut first_pass = tree_grid
-			.heights
-			.iter()
-			.map(|height| (false, *height, *height))
-			.collect::<Vec<_>>();
+	use super::{Edge, Pass, TreeGrid};
+
+	#[derive(Clone, Copy, Default)]
+	/// Which edges of the forest a tree is visible from, indexed by [`Edge`]
+	struct Visibility([bool; 4]);
 
-		// Now calculate, for each tree, if it is visible from the top or left sides.
-		// We must loop through indices rather than the vector itself because we must
-		// access a window of trees at a time - and LendingIterator doesn't exist yet.
-		(0..first_pass.len()).for_each(|i| {
-			// If this tree is on an edge, it is visible
-			if i == 0
-				|| i == first_pass.len()
-				|| i < tree_grid.width
-				|| i + tree_grid.width > first_pass.len()
-				|| (i % tree_grid.width) == 0
-				|| (i % tree_grid.width) == tree_grid.width - 1
-			{
-				first_pass[i].0 = true;
-			} else {
-				// Otherwise grab info about the trees above this tree and to the left. These necessarily exist because
-				// this tree is not on the edge.
-				let [above, left, this] = first_pass
-					.get_many_mut([i - tree_grid.width, i - 1, i])
-					.unwrap();
-
-				// Due to how we constructed first_pass, this tuple contains the height of the tree under consideration.
-				let height = this.1;
-
-				// Due to how we are iterating over first_pass, above and left have already been iterated over once,
-				// so their 1,2 tuple values contain the tree of greatest height in the above, left directions, respectively.
-				// So we can see this tree if its height is either greater than the height of any tree in the above or left directions.
-				this.0 |= height > above.1 || height > left.2;
-				// Then record the (potentially) new greatest height of trees in the above/left directions
-				this.1 = height.max(above.1);
-				this.2 = height.max(left.2);
+	impl Visibility {
+		/// Whether the tree is visible from a particular edge, or any edge at all
+		fn visible_from(self, edge: Edge) -> bool {
+			match edge {
+				Edge::Any => self.0.contains(&true),
+				edge => self.0[edge as usize],
 			}
-		});
-
-		// Next we are going to do the right, bottom edges, but first_pass contains a bunch of height information
-		// about trees in the above, left directions, so reset them to what we know about (the height of the tree in
-		// each grid position).
-		first_pass
-			.iter_mut()
-			.zip(tree_grid.heights.iter())
-			.for_each(|(pass, height)| {
-				pass.1 = *height;
-				pass.2 = *height;
-			});
-
-		// Same as above, but now the right,bottom edges.
-		// We reverse iteration to preserve the property that when we iterate over a tree, its right,bottom neighbor trees
-		// have already been iterated over and processed.
-		(0..first_pass.len()).rev().for_each(|i| {
-			if i == 0
-				|| i == first_pass.len()
-				|| i < tree_grid.width
-				|| i + tree_grid.width > first_pass.len()
-				|| (i % tree_grid.width) == 0
-				|| (i % tree_grid.width) == tree_grid.width - 1
-			{
-				first_pass[i].0 = true;
-			} else {
-				let [this, right, below] = first_pass
-					.get_many_mut([i, i + 1, i + tree_grid.width])
-					.unwrap();
-
-				let height = this.1;
-
-				// Note the |=, which will preserve visibility from the initial above,left pass
-				this.0 |= height > below.1 || height > right.2;
-				this.1 = height.max(below.1);
-				this.2 = height.max(right.2);
+		}
+	}
+
+	/// The partial results of part 1 while sweeping over a [`TreeGrid`]
+	pub(super) struct Visibilities<'a> {
+		tree_grid: &'a TreeGrid,
+		/// A grid of 3-tuples that indicate which edges a tree in the grid is visible from, and the
+		/// tallest tree in each of two directions (above,left in the forward pass, and below,right in the backward pass)
+		first_pass: Vec<(Visibility, u8, u8)>,
+	}
+
+	impl<'a> Visibilities<'a> {
+		pub(super) fn new(tree_grid: &'a TreeGrid) -> Self {
+			// We'll fill the heights in as we go, so by default the tallest trees we know about are the trees in
+			// those positions
+			let first_pass = tree_grid
+				.heights
+				.iter()
+				.map(|height| (Visibility::default(), *height, *height))
+				.collect::<Vec<_>>();
+
+			Self {
+				tree_grid,
+				first_pass,
+			}
+		}
+
+		/// Calculate, for the tree at index `i`, if it is visible from the top,left sides in the forward pass, or the
+		/// bottom,right sides in the backward pass. See [`TreeGrid::sweep`].
+		pub(super) fn visit(&mut self, pass: Pass, i: usize) {
+			let width = self.tree_grid.width;
+			let (row, col) = (i / width, i % width);
+			let first_pass = &mut self.first_pass;
+			// We'll overwrite the tallest trees we know about for this tree, so get its height from the grid itself
+			let tree_height = self.tree_grid.heights[i];
+
+			match pass {
+				Pass::Forward => {
+					// Grab info about the trees above this tree and to the left, if there are any. Due to how we are iterating over
+					// first_pass, above and left have already been iterated over once, so their 1,2 tuple values contain the tree of
+					// greatest height in the above, left directions, respectively.
+					let above = (row > 0).then(|| first_pass[i - width].1);
+					let left = (col > 0).then(|| first_pass[i - 1].2);
+
+					let this = &mut first_pass[i];
+
+					// We can see this tree from an edge if it's on that edge (so there's no tallest tree in that direction), or if its
+					// height is greater than the height of any tree in the direction of that edge. None is less than any Some.
+					this.0 .0[Edge::Top as usize] = above < Some(tree_height);
+					this.0 .0[Edge::Left as usize] = left < Some(tree_height);
+					// Then record the (potentially) new greatest height of trees in the above/left directions
+					this.1 = tree_height.max(above.unwrap_or(0));
+					this.2 = tree_height.max(left.unwrap_or(0));
+				}
+				// Same as above, but now the right,bottom edges. The right,bottom neighbors have already been visited in the
+				// backward pass, so their 1,2 tuple values are now the tallest trees in the below, right directions.
+				Pass::Backward => {
+					let below =
+						(row < self.tree_grid.height() - 1).then(|| first_pass[i + width].1);
+					let right = (col < width - 1).then(|| first_pass[i + 1].2);
+
+					let this = &mut first_pass[i];
+
+					this.0 .0[Edge::Bottom as usize] = below < Some(tree_height);
+					this.0 .0[Edge::Right as usize] = right < Some(tree_height);
+					this.1 = tree_height.max(below.unwrap_or(0));
+					this.2 = tree_height.max(right.unwrap_or(0));
+				}
 			}
-		});
+		}
 
-		// Count the number of visible trees
-		first_pass.iter().filter(|(vis, _, _)| *vis).count()
+		/// Count the number of trees visible from the edge we're interested in, once the sweep is done
+		pub(super) fn count(&self, edge: Edge) -> usize {
+			self.first_pass
+				.iter()
+				.filter(|(vis, _, _)| vis.visible_from(edge))
+				.count()
+		}
+
+		/// Whether each tree is visible from the edge we're interested in once the sweep is done, in the same order as `tree_grid.heights`
+		pub(super) fn into_visible(self, edge: Edge) -> impl Iterator<Item = bool> {
+			self.first_pass
+				.into_iter()
+				.map(move |(vis, _, _)| vis.visible_from(edge))
+		}
+	}
+
+	pub(super) fn visible_trees(tree_grid: &TreeGrid, edge: Edge) -> usize {
+		let mut visibilities = Visibilities::new(tree_grid);
+		tree_grid.sweep(|pass, i| visibilities.visit(pass, i));
+
+		visibilities.count(edge)
+	}
+
+	/// Whether every tree in the grid is visible from `edge`, in the same order as `tree_grid.heights`
+	pub(super) fn visibility(tree_grid: &TreeGrid, edge: Edge) -> impl Iterator<Item = bool> {
+		let mut visibilities = Visibilities::new(tree_grid);
+		tree_grid.sweep(|pass, i| visibilities.visit(pass, i));
+
+		visibilities.into_visible(edge)
+	}
+
+	/// The row and column of every tree which is visible from `edge`, or of every tree which is hidden from it if `invert`,
+	/// in the same order as `tree_grid.heights`
+	pub(super) fn visible_positions(
+		tree_grid: &TreeGrid,
+		edge: Edge,
+		invert: bool,
+	) -> Vec<(usize, usize)> {
+		visibility(tree_grid, edge)
+			.enumerate()
+			.filter(|(_, visible)| *visible != invert)
+			.map(|(i, _)| (i / tree_grid.width, i % tree_grid.width))
+			.collect()
 	}
 }
 
 mod part2 {
-	use super::TreeGrid;
+	use std::{cmp::Reverse, collections::BinaryHeap};
+
+	use anyhow::{anyhow, Result};
+
+	use super::{Pass, TreeGrid};
 
 	#[derive(Clone, Copy)]
 	/// Convenience struct for keeping track of how far can be seen in a direction from a tree,
@@ -164,21 +343,30 @@ mod part2 {
 	}
 
 	/// A convenience struct for keeping track of how far we can see from a tree in every direction.
+	/// The diagonal directions are only filled in when looking diagonally.
 	struct ViewDirections {
 		above: Option<ViewDistance>,
 		left: Option<ViewDistance>,
 		right: Option<ViewDistance>,
 		below: Option<ViewDistance>,
+		up_left: Option<ViewDistance>,
+		up_right: Option<ViewDistance>,
+		down_left: Option<ViewDistance>,
+		down_right: Option<ViewDistance>,
 	}
 
 	#[derive(Clone, Copy)]
 	/// A convenience struct for encoding which direction we want to look.
-	/// Above and Below must know about how wide each row in the tree grid is.
+	/// Every direction which moves between rows must know about how wide each row in the tree grid is.
 	enum Direction {
 		Above(usize),
 		Left,
 		Right,
 		Below(usize),
+		UpLeft(usize),
+		UpRight(usize),
+		DownLeft(usize),
+		DownRight(usize),
 	}
 
 	impl Direction {
@@ -189,8 +377,36 @@ mod part2 {
 				Direction::Left => idx - mult,
 				Direction::Right => idx + mult,
 				Direction::Below(width) => idx + mult * width,
+				// Moving a column left or right as well as a row up or down. These would wrap around onto the next row
+				// from the edges of the grid, so they must not be used to look past the left or right edge.
+				Direction::UpLeft(width) => idx - mult * (width + 1),
+				Direction::UpRight(width) => idx - mult * (width - 1),
+				Direction::DownLeft(width) => idx + mult * (width - 1),
+				Direction::DownRight(width) => idx + mult * (width + 1),
 			}
 		}
+
+		/// Move an index (`idx`) one step in a certain direction, wrapping around to the opposite edge of a grid
+		/// with the given dimensions instead of going past an edge.
+		fn wrapping_step(&self, idx: usize, width: usize, height: usize) -> usize {
+			let (row, col) = (idx / width, idx % width);
+			// The neighboring rows and columns, wrapping around at the edges
+			let (up, down) = ((row + height - 1) % height, (row + 1) % height);
+			let (left, right) = ((col + width - 1) % width, (col + 1) % width);
+
+			let (row, col) = match self {
+				Direction::Above(_) => (up, col),
+				Direction::Left => (row, left),
+				Direction::Right => (row, right),
+				Direction::Below(_) => (down, col),
+				Direction::UpLeft(_) => (up, left),
+				Direction::UpRight(_) => (up, right),
+				Direction::DownLeft(_) => (down, left),
+				Direction::DownRight(_) => (down, right),
+			};
+
+			row * width + col
+		}
 	}
 
 	impl ViewDirections {
@@ -201,6 +417,10 @@ mod part2 {
 				Direction::Left => self.left,
 				Direction::Right => self.right,
 				Direction::Below(_) => self.below,
+				Direction::UpLeft(_) => self.up_left,
+				Direction::UpRight(_) => self.up_right,
+				Direction::DownLeft(_) => self.down_left,
+				Direction::DownRight(_) => self.down_right,
 			}
 		}
 	}
@@ -243,118 +463,515 @@ mod part2 {
 		}
 	}
 
-	pub(super) fn highest_scenic_score(tree_grid: &TreeGrid) -> usize {
-		// Default initialise the views vector, which keep track of our partial results
-		// for calculating full results and also calculating partial results of other trees
-		let mut views = tree_grid
-			.heights
-			.iter()
-			.map(|_| ViewDirections {
-				above: None,
-				left: None,
-				right: None,
-				below: None,
-			})
-			.collect::<Vec<_>>();
+	/// Find the `ViewDistance` from a tree in a particular direction, or an edge's `ViewDistance` if the tree is on an edge
+	/// in that direction
+	fn view_distance_unless_edge(
+		views: &[ViewDirections],
+		tree_grid: &TreeGrid,
+		idx: usize,
+		direction: Direction,
+		on_edge: bool,
+	) -> ViewDistance {
+		if on_edge {
+			ViewDistance::edge()
+		} else {
+			find_view_distance(views, tree_grid, idx, direction)
+		}
+	}
 
-		// Similar to part 1, do a first partial pass that only calculates partial results in the above,left directions.
-		// Since we are iterating forward, the partial results for all of the trees in each tree's above,left directions
-		// have already been calculated, so we can use those.
-		(0..views.len()).for_each(|i| {
-			// Top left corner
-			if i == 0 {
-				views[i].above = Some(ViewDistance::edge());
-				views[i].left = Some(ViewDistance::edge());
-			}
-			// Top edge
-			else if i < tree_grid.width {
-				views[i].above = Some(ViewDistance::edge());
-				views[i].left = Some(find_view_distance(&views, tree_grid, i, Direction::Left));
-			}
-			// Left edge
-			else if i % tree_grid.width == 0 {
-				views[i].above = Some(find_view_distance(
-					&views,
-					tree_grid,
-					i,
-					Direction::Above(tree_grid.width),
-				));
-				views[i].left = Some(ViewDistance::edge());
-			}
-			// Inside
-			else {
-				views[i].above = Some(find_view_distance(
-					&views,
-					tree_grid,
-					i,
-					Direction::Above(tree_grid.width),
-				));
-				views[i].left = Some(find_view_distance(&views, tree_grid, i, Direction::Left));
+	/// The scenic score of a tree, which is the product of its view distances. Large forests can have scores far
+	/// too large for a usize, especially looking diagonally, so this errors rather than overflowing.
+	pub(super) fn scenic_score(distances: impl IntoIterator<Item = usize>) -> Result<u128> {
+		distances.into_iter().try_fold(1, |score: u128, distance| {
+			u128::try_from(distance)
+				.ok()
+				.and_then(|distance| score.checked_mul(distance))
+				.ok_or_else(|| anyhow!("A scenic score is too large to represent"))
+		})
+	}
+
+	/// How far can be seen from a tree in a particular direction when the forest wraps around. There are no edges to
+	/// build partial results up from, so this looks at every tree in the way. Sight stops at the first tree at least
+	/// as tall, or just before getting back around to the tree it started from.
+	fn wrapped_view_distance(tree_grid: &TreeGrid, idx: usize, direction: Direction) -> usize {
+		let (width, height) = (tree_grid.width, tree_grid.height());
+		let mut distance = 0;
+		let mut next = direction.wrapping_step(idx, width, height);
+
+		while next != idx {
+			distance += 1;
+			if tree_grid.heights[next] >= tree_grid.heights[idx] {
+				break;
 			}
-		});
-
-		// Similar to part 1, now calculate partial results for right,below directions. Reverse iteration
-		// to keep property allowing us to use other partial results.
-		(0..views.len()).rev().for_each(|i| {
-			// Bottom right corner
-			if i == views.len() {
-				views[i].right = Some(ViewDistance::edge());
-				views[i].below = Some(ViewDistance::edge());
+
+			next = direction.wrapping_step(next, width, height);
+		}
+
+		distance
+	}
+
+	/// The scenic score of every tree when the forest wraps around, as in [`wrapped_view_distance`]
+	fn wrapped_scenic_scores(tree_grid: &TreeGrid, diagonals: bool) -> Result<Vec<u128>> {
+		let width = tree_grid.width;
+		let mut directions = vec![
+			Direction::Above(width),
+			Direction::Left,
+			Direction::Right,
+			Direction::Below(width),
+		];
+		if diagonals {
+			directions.extend([
+				Direction::UpLeft(width),
+				Direction::UpRight(width),
+				Direction::DownLeft(width),
+				Direction::DownRight(width),
+			]);
+		}
+
+		(0..tree_grid.heights.len())
+			.map(|idx| {
+				scenic_score(
+					directions
+						.iter()
+						.map(|direction| wrapped_view_distance(tree_grid, idx, *direction)),
+				)
+			})
+			.collect()
+	}
+
+	/// The partial results of part 2 while sweeping over a [`TreeGrid`]
+	pub(super) struct Views<'a> {
+		tree_grid: &'a TreeGrid,
+		view_directions: Vec<ViewDirections>,
+		/// Whether to look diagonally as well
+		diagonals: bool,
+	}
+
+	impl<'a> Views<'a> {
+		pub(super) fn new(tree_grid: &'a TreeGrid, diagonals: bool) -> Self {
+			// Default initialise the views vector, which keep track of our partial results
+			// for calculating full results and also calculating partial results of other trees
+			let views = tree_grid
+				.heights
+				.iter()
+				.map(|_| ViewDirections {
+					above: None,
+					left: None,
+					right: None,
+					below: None,
+					up_left: None,
+					up_right: None,
+					down_left: None,
+					down_right: None,
+				})
+				.collect::<Vec<_>>();
+
+			Self {
+				tree_grid,
+				view_directions: views,
+				diagonals,
 			}
-			// Bottom edge
-			else if i + tree_grid.width > views.len() {
-				views[i].right = Some(find_view_distance(&views, tree_grid, i, Direction::Right));
-				views[i].below = Some(ViewDistance::edge());
+		}
+
+		/// Calculate the partial results for the tree at index `i` in the above,left directions in the forward pass,
+		/// or the right,below directions in the backward pass. See [`TreeGrid::sweep`].
+		pub(super) fn visit(&mut self, pass: Pass, i: usize) {
+			let tree_grid = self.tree_grid;
+			let views = &mut self.view_directions;
+			// Which column the tree is in
+			let col = i % tree_grid.width;
+
+			match pass {
+				// Similar to part 1, do a first partial pass that only calculates partial results in the above,left directions.
+				// Since we are iterating forward, the partial results for all of the trees in each tree's above,left directions
+				// have already been calculated, so we can use those.
+				Pass::Forward => {
+					// Top left corner
+					if i == 0 {
+						views[i].above = Some(ViewDistance::edge());
+						views[i].left = Some(ViewDistance::edge());
+					}
+					// Top edge
+					else if i < tree_grid.width {
+						views[i].above = Some(ViewDistance::edge());
+						views[i].left =
+							Some(find_view_distance(views, tree_grid, i, Direction::Left));
+					}
+					// Left edge
+					else if col == 0 {
+						views[i].above = Some(find_view_distance(
+							views,
+							tree_grid,
+							i,
+							Direction::Above(tree_grid.width),
+						));
+						views[i].left = Some(ViewDistance::edge());
+					}
+					// Inside
+					else {
+						views[i].above = Some(find_view_distance(
+							views,
+							tree_grid,
+							i,
+							Direction::Above(tree_grid.width),
+						));
+						views[i].left =
+							Some(find_view_distance(views, tree_grid, i, Direction::Left));
+					}
+				}
+				// Similar to part 1, now calculate partial results for right,below directions. Reverse iteration
+				// to keep property allowing us to use other partial results.
+				Pass::Backward => {
+					// Bottom right corner
+					if i == views.len() {
+						views[i].right = Some(ViewDistance::edge());
+						views[i].below = Some(ViewDistance::edge());
+					}
+					// Bottom edge
+					else if i + tree_grid.width > views.len() {
+						views[i].right =
+							Some(find_view_distance(views, tree_grid, i, Direction::Right));
+						views[i].below = Some(ViewDistance::edge());
+					}
+					// Right edge
+					else if col == tree_grid.width - 1 {
+						views[i].right = Some(ViewDistance::edge());
+						views[i].below = Some(find_view_distance(
+							views,
+							tree_grid,
+							i,
+							Direction::Below(tree_grid.width),
+						));
+					}
+					// Inside
+					else {
+						views[i].right =
+							Some(find_view_distance(views, tree_grid, i, Direction::Right));
+						views[i].below = Some(find_view_distance(
+							views,
+							tree_grid,
+							i,
+							Direction::Below(tree_grid.width),
+						));
+					}
+				}
 			}
-			// Right edge
-			else if i % tree_grid.width == tree_grid.width - 1 {
-				views[i].right = Some(ViewDistance::edge());
-				views[i].below = Some(find_view_distance(
-					&views,
-					tree_grid,
-					i,
-					Direction::Below(tree_grid.width),
-				));
+
+			if self.diagonals {
+				self.visit_diagonals(pass, i);
 			}
-			// Inside
-			else {
-				views[i].right = Some(find_view_distance(&views, tree_grid, i, Direction::Right));
-				views[i].below = Some(find_view_distance(
-					&views,
-					tree_grid,
-					i,
-					Direction::Below(tree_grid.width),
-				));
+		}
+
+		/// The same as [`Views::visit`], but for the diagonal directions - up,left and up,right in the forward pass,
+		/// and down,left and down,right in the backward pass. The trees in those directions are in earlier rows
+		/// (or later rows, going backwards), so they have already been visited in the same pass.
+		fn visit_diagonals(&mut self, pass: Pass, i: usize) {
+			let tree_grid = self.tree_grid;
+			let views = &mut self.view_directions;
+			let width = tree_grid.width;
+			let col = i % width;
+			// Which edges the tree is on. Diagonal directions can't tell when they've gone past the side of the grid,
+			// since that just wraps around onto another row, so trees on those edges can't look past them.
+			let (top, left, right, bottom) = (
+				i < width,
+				col == 0,
+				col == width - 1,
+				i + width >= views.len(),
+			);
+
+			match pass {
+				Pass::Forward => {
+					views[i].up_left = Some(view_distance_unless_edge(
+						views,
+						tree_grid,
+						i,
+						Direction::UpLeft(width),
+						top || left,
+					));
+					views[i].up_right = Some(view_distance_unless_edge(
+						views,
+						tree_grid,
+						i,
+						Direction::UpRight(width),
+						top || right,
+					));
+				}
+				Pass::Backward => {
+					views[i].down_left = Some(view_distance_unless_edge(
+						views,
+						tree_grid,
+						i,
+						Direction::DownLeft(width),
+						bottom || left,
+					));
+					views[i].down_right = Some(view_distance_unless_edge(
+						views,
+						tree_grid,
+						i,
+						Direction::DownRight(width),
+						bottom || right,
+					));
+				}
 			}
-		});
+		}
 
-		views
-			.iter()
-			.map(|v| {
-				// Scenic scores are product of distances in each direction (partial results)
-				v.above.unwrap().distance
-					* v.below.unwrap().distance
-					* v.left.unwrap().distance
-					* v.right.unwrap().distance
-			})
-			// Find max scenic score
+		/// The scenic score of every tree in the grid once the sweep is done, in the same order as `tree_grid.heights`,
+		/// or an error if any of them is too large to represent
+		pub(super) fn into_scores(self) -> Result<Vec<u128>> {
+			self.view_directions
+				.into_iter()
+				.map(|v| {
+					// Scenic scores are product of distances in each direction (partial results).
+					// Diagonal directions which weren't looked in don't contribute.
+					let diagonal = [v.up_left, v.up_right, v.down_left, v.down_right]
+						.into_iter()
+						.flatten()
+						.map(|view| view.distance);
+
+					scenic_score(
+						[v.above, v.below, v.left, v.right]
+							.into_iter()
+							.map(|view| view.unwrap().distance)
+							.chain(diagonal),
+					)
+				})
+				.collect()
+		}
+	}
+
+	/// Calculate the scenic score of every tree in the grid, in the same order as `tree_grid.heights`.
+	/// If `wrap`, the forest wraps around at its edges. Errors if any scenic score is too large to represent.
+	pub(super) fn scenic_scores(
+		tree_grid: &TreeGrid,
+		diagonals: bool,
+		wrap: bool,
+	) -> Result<Vec<u128>> {
+		if wrap {
+			return wrapped_scenic_scores(tree_grid, diagonals);
+		}
+
+		let mut views = Views::new(tree_grid, diagonals);
+		tree_grid.sweep(|pass, i| views.visit(pass, i));
+
+		views.into_scores()
+	}
+
+	pub(super) fn highest_scenic_score(
+		tree_grid: &TreeGrid,
+		diagonals: bool,
+		wrap: bool,
+	) -> Result<u128> {
+		// Find max scenic score
+		Ok(scenic_scores(tree_grid, diagonals, wrap)?
+			.into_iter()
 			.max()
-			.unwrap()
+			.unwrap())
+	}
+
+	pub(super) fn total_scenic_score(
+		tree_grid: &TreeGrid,
+		diagonals: bool,
+		wrap: bool,
+	) -> Result<u64> {
+		// Sum as u64, since a large forest can have many large scores, but error rather than overflowing
+		scenic_scores(tree_grid, diagonals, wrap)?
+			.into_iter()
+			.try_fold(0, |total: u64, score| {
+				u64::try_from(score)
+					.ok()
+					.and_then(|score| total.checked_add(score))
+					.ok_or_else(|| anyhow!("The total scenic score is too large to represent"))
+			})
+	}
+
+	/// Find the `k` highest scenic scores as `(score, row, col)`, in descending order
+	pub(super) fn top_scenic_scores(
+		tree_grid: &TreeGrid,
+		k: usize,
+		diagonals: bool,
+		wrap: bool,
+	) -> Result<Vec<(u128, usize, usize)>> {
+		// Attach the position of each tree to its score. Convenience Reverse for min-heap.
+		let mut score_iter = scenic_scores(tree_grid, diagonals, wrap)?
+			.into_iter()
+			.enumerate()
+			.map(|(i, score)| Reverse((score, i / tree_grid.width, i % tree_grid.width)));
+
+		// Same technique as day 1 - a min-heap of the k best scores seen so far, starting with the first k trees.
+		let mut heap = score_iter.by_ref().take(k).collect::<BinaryHeap<_>>();
+
+		// A tree's score is one of the top k so far if it beats the smallest of them, which is on top of the min-heap.
+		// Note the heap stores Reverse(x), so the comparison is backwards. If k is 0, the heap is empty and nothing is kept.
+		for x in score_iter {
+			if heap.peek().is_some_and(|smallest| smallest > &x) {
+				heap.pop();
+				heap.push(x);
+			}
+		}
+
+		// Sorting the Reverse(x)s in ascending order puts the scores in descending order
+		Ok(heap.into_sorted_vec().into_iter().map(|x| x.0).collect())
+	}
+
+	/// Find every tree with a scenic score of at least `min` as `(row, col, score)`, row by row
+	pub(super) fn trees_with_min_score(
+		tree_grid: &TreeGrid,
+		min: u128,
+		diagonals: bool,
+		wrap: bool,
+	) -> Result<Vec<(usize, usize, u128)>> {
+		Ok(scenic_scores(tree_grid, diagonals, wrap)?
+			.into_iter()
+			.enumerate()
+			.filter(|(_, score)| *score >= min)
+			.map(|(i, score)| (i / tree_grid.width, i % tree_grid.width, score))
+			.collect())
 	}
 }
-fn main() -> Result<()> {
-	let args = Args::parse();
+/// Both variants of the problem, sharing a single sweep through the grid.
+/// A wrapping forest can't be swept, so its scenic scores are found separately.
+fn both(tree_grid: &TreeGrid, edge: Edge, diagonals: bool, wrap: bool) -> Result<(usize, u128)> {
+	if wrap {
+		return Ok((
+			part1::visible_trees(tree_grid, edge),
+			part2::highest_scenic_score(tree_grid, diagonals, true)?,
+		));
+	}
+
+	let mut visibilities = part1::Visibilities::new(tree_grid);
+	let mut views = part2::Views::new(tree_grid, diagonals);
+
+	tree_grid.sweep(|pass, i| {
+		visibilities.visit(pass, i);
+		views.visit(pass, i);
+	});
+
+	Ok((
+		visibilities.count(edge),
+		views.into_scores()?.into_iter().max().unwrap(),
+	))
+}
+
+/// Scale values so that the largest is 255, for use as the intensities of pixels in an image.
+/// If every value is 0, so are the intensities.
+fn intensities(values: &[u128]) -> Vec<u8> {
+	let max = values.iter().copied().max().unwrap_or(0).max(1);
 
-	let mut file = File::open(args.input_file)?;
+	values
+		.iter()
+		.map(|value| {
+			// Dividing first loses precision, so only do it when multiplying first would overflow. Then the largest value
+			// is at least 255, so it can't be divided by 0.
+			let scaled = value
+				.checked_mul(255)
+				.map_or_else(|| value / (max / 255), |value| value / max);
+			// The scaled value is at most 255, so it always fits
+			u8::try_from(scaled).unwrap()
+		})
+		.collect()
+}
+
+/// Write a grayscale image with one pixel per tree as a binary PPM, with every pixel having the same red, green, and blue intensity
+fn write_ppm<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[u8]) -> io::Result<()> {
+	writeln!(out, "P6\n{width} {height}\n255")?;
+
+	for pixel in pixels {
+		out.write_all(&[*pixel; 3])?;
+	}
+
+	Ok(())
+}
 
+/// Read the whole of `reader` and parse it as a grid of trees
+fn read_grid<R: Read>(mut reader: R) -> Result<TreeGrid> {
 	let mut input = String::new();
-	file.read_to_string(&mut input)?;
+	reader.read_to_string(&mut input)?;
 
-	let tree_grid = input.parse()?;
+	input.parse()
+}
+
+fn main() -> Result<()> {
+	let args = Args::parse();
+
+	let tree_grid = if args.input_file.as_os_str() == "-" {
+		read_grid(io::stdin().lock())?
+	} else {
+		read_grid(File::open(args.input_file)?)?
+	};
 
 	match args.mode {
-		Mode::NumVisible => println!("{}", part1::visible_trees(&tree_grid)),
-		Mode::ScenicScore => println!("{}", part2::highest_scenic_score(&tree_grid)),
+		Mode::NumVisible if args.visible.invert || args.visible.list => {
+			let trees = part1::visible_positions(&tree_grid, args.direction, args.visible.invert);
+			println!("{}", trees.len());
+
+			if args.visible.list {
+				for (row, col) in trees {
+					println!("{row},{col}");
+				}
+			}
+		}
+		Mode::NumVisible => println!("{}", part1::visible_trees(&tree_grid, args.direction)),
+		Mode::Both => {
+			let (visible, scenic_score) =
+				both(&tree_grid, args.direction, args.diagonals, args.wrap)?;
+			println!("Visible: {visible}");
+			println!("Scenic score: {scenic_score}");
+		}
+		Mode::PerimeterProduct => println!("{}", perimeter_product(&tree_grid)),
+		Mode::ScenicSum => println!(
+			"{}",
+			part2::total_scenic_score(&tree_grid, args.diagonals, args.wrap)?
+		),
+		Mode::ScenicScore => match (args.top_k, args.min_scenic) {
+			(Some(k), _) => {
+				for (rank, (score, row, col)) in
+					part2::top_scenic_scores(&tree_grid, k, args.diagonals, args.wrap)?
+						.iter()
+						.enumerate()
+				{
+					println!("{}. {score} (row {row}, column {col})", rank + 1);
+				}
+			}
+			(None, Some(min)) => {
+				for (row, col, score) in
+					part2::trees_with_min_score(&tree_grid, min, args.diagonals, args.wrap)?
+				{
+					println!("{row},{col},{score}");
+				}
+			}
+			(None, None) => println!(
+				"{}",
+				part2::highest_scenic_score(&tree_grid, args.diagonals, args.wrap)?
+			),
+		},
+	}
+
+	if let Some(path) = &args.image {
+		// All of the scenic score modes share the scenic score image
+		let values = match args.mode {
+			Mode::NumVisible => part1::visibility(&tree_grid, args.direction)
+				.map(u128::from)
+				.collect::<Vec<_>>(),
+			Mode::ScenicScore | Mode::ScenicSum | Mode::Both => {
+				part2::scenic_scores(&tree_grid, args.diagonals, args.wrap)?
+			}
+			Mode::PerimeterProduct => tree_grid.heights.iter().copied().map(u128::from).collect(),
+		};
+
+		let mut out = io::BufWriter::new(File::create(path)?);
+		write_ppm(
+			&mut out,
+			tree_grid.width,
+			tree_grid.height(),
+			&intensities(&values),
+		)?;
+	}
+
+	if args.edges {
+		println!(
+			"Trees on the edges: {}",
+			edge_count(tree_grid.width, tree_grid.height())
+		);
 	}
 
 	Ok(())
@@ -374,7 +991,293 @@ mod tests {
 	#[test]
 	fn example() {
 		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
-		assert_eq!(part1::visible_trees(&tree_grid), 21);
-		assert_eq!(part2::highest_scenic_score(&tree_grid), 8);
+		assert_eq!(part1::visible_trees(&tree_grid, Edge::Any), 21);
+		assert_eq!(
+			part2::highest_scenic_score(&tree_grid, false, false).unwrap(),
+			8
+		);
+		assert_eq!(edge_count(tree_grid.width, tree_grid.height()), 16);
+	}
+
+	#[test]
+	fn parse_whitespace() {
+		let tree_grid = PROMPT
+			.lines()
+			.flat_map(|line| [line, "  \r\n"])
+			.collect::<String>()
+			.parse::<TreeGrid>()
+			.unwrap();
+
+		assert_eq!(tree_grid.width, 5);
+		assert_eq!(tree_grid.heights.len(), 25);
+		assert_eq!(part1::visible_trees(&tree_grid, Edge::Any), 21);
+		assert_eq!(
+			part2::highest_scenic_score(&tree_grid, false, false).unwrap(),
+			8
+		);
+
+		assert!("123\n45\n".parse::<TreeGrid>().is_err());
+		assert!("123\n4é6\n".parse::<TreeGrid>().is_err());
+		assert!("123\n456\n\n\n".parse::<TreeGrid>().is_ok());
+		// Blank lines between rows aren't skipped, and errors name the row as it is in the input
+		assert_eq!(
+			"123\n\n456\n"
+				.parse::<TreeGrid>()
+				.err()
+				.map(|error| error.to_string()),
+			Some("Row 2 is empty".to_string())
+		);
+		assert_eq!(
+			"123\n  \r\n456\n"
+				.parse::<TreeGrid>()
+				.err()
+				.map(|error| error.to_string()),
+			Some("Row 2 is empty".to_string())
+		);
+	}
+
+	#[test]
+	fn from_reader() {
+		let tree_grid = read_grid(std::io::Cursor::new(PROMPT)).unwrap();
+		assert_eq!(both(&tree_grid, Edge::Any, false, false).unwrap(), (21, 8));
+	}
+
+	#[test]
+	fn test_both() {
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		assert_eq!(both(&tree_grid, Edge::Any, false, false).unwrap(), (21, 8));
+	}
+
+	#[test]
+	fn scenic_sum() {
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		assert_eq!(
+			part2::total_scenic_score(&tree_grid, false, false).unwrap(),
+			27
+		);
+
+		// The 9 in the middle of this forest has a scenic score of 299^4 looking straight, and every other tree has a scenic
+		// score of 1 wrapping around. Looking diagonally as well, the 9 alone has a scenic score of 299^8, which is too large.
+		let mut grid = format!("{}\n", "0".repeat(300)).repeat(300);
+		grid.replace_range(150 * 301 + 150..150 * 301 + 151, "9");
+		let tree_grid = grid.parse::<TreeGrid>().unwrap();
+		assert_eq!(
+			part2::total_scenic_score(&tree_grid, false, true).unwrap(),
+			299u64.pow(4) + 300 * 300 - 1
+		);
+		assert!(part2::total_scenic_score(&tree_grid, true, true).is_err());
+	}
+
+	#[test]
+	fn top_k() {
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		assert_eq!(
+			part2::top_scenic_scores(&tree_grid, 3, false, false).unwrap(),
+			vec![(8, 3, 2), (6, 2, 1), (4, 1, 2)]
+		);
+		assert!(part2::top_scenic_scores(&tree_grid, 0, false, false)
+			.unwrap()
+			.is_empty());
+		assert_eq!(
+			part2::top_scenic_scores(&tree_grid, 100, false, false)
+				.unwrap()
+				.len(),
+			25
+		);
+	}
+
+	#[test]
+	fn min_scenic() {
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		assert_eq!(
+			part2::trees_with_min_score(&tree_grid, 4, false, false).unwrap(),
+			vec![(1, 2, 4), (2, 1, 6), (3, 2, 8)]
+		);
+		assert!(part2::trees_with_min_score(&tree_grid, 9, false, false)
+			.unwrap()
+			.is_empty());
+		assert_eq!(
+			part2::trees_with_min_score(&tree_grid, 0, false, false)
+				.unwrap()
+				.len(),
+			25
+		);
+	}
+
+	#[test]
+	fn direction() {
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		assert_eq!(part1::visible_trees(&tree_grid, Edge::Left), 11);
+		assert_eq!(part1::visible_trees(&tree_grid, Edge::Top), 10);
+		assert_eq!(part1::visible_trees(&tree_grid, Edge::Any), 21);
+	}
+
+	#[test]
+	fn diagonals() {
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		assert_eq!(
+			part2::highest_scenic_score(&tree_grid, false, false).unwrap(),
+			8
+		);
+		// The 5 in the middle of the fourth row can see 2 trees up and to the right, and 1 tree in every other diagonal direction,
+		// doubling its scenic score of 8
+		assert_eq!(
+			part2::highest_scenic_score(&tree_grid, true, false).unwrap(),
+			16
+		);
+		assert_eq!(both(&tree_grid, Edge::Any, true, false).unwrap(), (21, 16));
+
+		// A grid which isn't square, so diagonals which wrapped around onto the next row would be noticed
+		let tree_grid = "1234321
+2105912
+3999993
+1204021"
+			.parse::<TreeGrid>()
+			.unwrap();
+		assert_eq!(
+			part2::top_scenic_scores(&tree_grid, 3, false, false).unwrap(),
+			vec![(8, 1, 4), (3, 1, 3), (2, 2, 5)]
+		);
+		assert_eq!(
+			part2::top_scenic_scores(&tree_grid, 3, true, false).unwrap(),
+			vec![(8, 2, 2), (8, 1, 4), (4, 2, 4)]
+		);
+	}
+
+	#[test]
+	fn large_scenic_score() {
+		// A 9 in the middle of a forest of 0s can see to every edge. Its diagonal scenic score is 300^3 * 299^5,
+		// which is too large for a u64.
+		let mut grid = format!("{}\n", "0".repeat(600)).repeat(600);
+		grid.replace_range(300 * 601 + 300..300 * 601 + 301, "9");
+		let tree_grid = grid.parse::<TreeGrid>().unwrap();
+		assert_eq!(
+			part2::highest_scenic_score(&tree_grid, true, false).unwrap(),
+			300u128.pow(3) * 299u128.pow(5)
+		);
+
+		// Two of the largest possible view distances still fit, but three don't
+		assert_eq!(
+			part2::scenic_score([usize::MAX; 2]).unwrap(),
+			u128::from(u64::MAX).pow(2)
+		);
+		assert!(part2::scenic_score([usize::MAX; 3]).is_err());
+		assert_eq!(part2::scenic_score([]).unwrap(), 1);
+	}
+
+	#[test]
+	fn hidden() {
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		let hidden = part1::visible_positions(&tree_grid, Edge::Any, true);
+
+		// Every tree which isn't visible, all of which are inside the forest
+		assert_eq!(hidden.len(), 25 - 21);
+		assert!(hidden.contains(&(1, 3)));
+		assert!(hidden.contains(&(2, 2)));
+		assert_eq!(hidden, [(1, 3), (2, 2), (3, 1), (3, 3)]);
+		assert_eq!(
+			part1::visible_positions(&tree_grid, Edge::Any, false).len(),
+			21
+		);
+	}
+
+	#[test]
+	fn perimeter() {
+		// There's a tree of height 0 on the top edge
+		assert_eq!(perimeter_product(&PROMPT.parse().unwrap()), 0);
+		// Every tree but the one in the middle
+		assert_eq!(
+			perimeter_product(&"123\n456\n789".parse().unwrap()),
+			2 * 3 * 4 * 6 * 7 * 8 * 9
+		);
+		// 116 trees of height 9 on the edges, which is far too large for a u64
+		let grid = format!("{}\n", "9".repeat(30)).repeat(30);
+		assert_eq!(
+			perimeter_product(&grid.parse().unwrap()),
+			13_915_497_905_985_552_161
+		);
+	}
+
+	#[test]
+	fn wrap() {
+		let tree_grid = "131\n292\n131".parse::<TreeGrid>().unwrap();
+
+		// Every tree is on an edge, except the one in the middle which can see one tree in every direction
+		assert_eq!(
+			part2::scenic_scores(&tree_grid, false, false).unwrap(),
+			[0, 0, 0, 0, 1, 0, 0, 0, 0]
+		);
+		// Wrapping around, the tree in the middle can now see both other trees in every direction before getting back to itself,
+		// and the trees in the middle of each edge can see past the 1s around to each other
+		assert_eq!(
+			part2::scenic_scores(&tree_grid, false, true).unwrap(),
+			[1, 4, 1, 4, 16, 4, 1, 4, 1]
+		);
+		assert_eq!(
+			part2::highest_scenic_score(&tree_grid, false, true).unwrap(),
+			16
+		);
+		assert_eq!(both(&tree_grid, Edge::Any, false, true).unwrap(), (9, 16));
+
+		// A single tree can't see anything, even wrapping around
+		let tree_grid = "5".parse::<TreeGrid>().unwrap();
+		assert_eq!(
+			part2::highest_scenic_score(&tree_grid, true, true).unwrap(),
+			0
+		);
+
+		// The example forest is much more scenic when it wraps around
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		assert!(part2::highest_scenic_score(&tree_grid, false, true).unwrap() > 8);
+
+		// A 9 in a forest of 0s can see every other tree in its row, column, and diagonals before getting back around to itself,
+		// for a scenic score of 299^8, which is too large for a u64
+		let mut grid = format!("{}\n", "0".repeat(300)).repeat(300);
+		grid.replace_range(150 * 301 + 150..150 * 301 + 151, "9");
+		let tree_grid = grid.parse::<TreeGrid>().unwrap();
+		assert_eq!(
+			part2::highest_scenic_score(&tree_grid, true, true).unwrap(),
+			299u128.pow(8)
+		);
+	}
+
+	#[test]
+	fn image() {
+		let tree_grid = PROMPT.parse::<TreeGrid>().unwrap();
+		let scores = part2::scenic_scores(&tree_grid, false, false).unwrap();
+		let mut out = Vec::new();
+		write_ppm(
+			&mut out,
+			tree_grid.width,
+			tree_grid.height(),
+			&intensities(&scores),
+		)
+		.unwrap();
+
+		let header = b"P6\n5 5\n255\n";
+		assert!(out.starts_with(header));
+		assert_eq!(out.len(), header.len() + 5 * 5 * 3);
+		// The tree with the highest scenic score (8), in row 3 and column 2, is white
+		let pixel = header.len() + (3 * 5 + 2) * 3;
+		assert_eq!(out[pixel..pixel + 3], [255; 3]);
+
+		let visible = part1::visibility(&tree_grid, Edge::Any)
+			.map(u128::from)
+			.collect::<Vec<_>>();
+		// Visible trees are white, and hidden trees are black
+		assert!(intensities(&visible)
+			.iter()
+			.zip(&visible)
+			.all(|(intensity, visible)| u128::from(*intensity) == visible * 255));
+		assert_eq!(intensities(&[0, 0]), [0, 0]);
+		// Scores far too large to multiply by 255 still scale
+		assert_eq!(
+			intensities(&[u128::MAX, u128::MAX / 2, u128::from(u64::MAX), 0]),
+			[255, 127, 0, 0]
+		);
+		assert_eq!(
+			intensities(&[u128::from(u64::MAX), u128::from(u64::MAX) / 2]),
+			[255, 127]
+		);
 	}
 }